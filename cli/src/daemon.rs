@@ -0,0 +1,212 @@
+//! 守护进程模式。
+//!
+//! `xiaoai daemon` 在 Unix 套接字上监听，保持已加载的 [`Xiaoai`] 和其中的连接，
+//! 之后的 `say`、`volume` 和 `status` 命令会转发给它执行，省去每次加载认证文件和建立 TLS 连接。
+//!
+//! 协议是每行一个 JSON：请求为 `{"command": ..., "deviceId": ..., "json": ..., "quiet": ...}`
+//! 及命令的参数，响应为 `{"output": ...}` 或 `{"error": ..., "offline": ...}`，其中 `output`
+//! 是直接执行时输出的内容，`offline` 表示是否因为设备离线而失败。
+
+use std::{
+    fmt, fs,
+    io::{self, ErrorKind},
+    os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt},
+    path::Path,
+};
+
+use anyhow::{Context, anyhow, bail, ensure};
+use miai::Xiaoai;
+use serde_json::{Value, json};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+};
+
+use crate::display::DisplayPlayerStatus;
+
+/// 守护进程执行命令时设备离线，以便转发时同样以 `EXIT_DEVICE_OFFLINE` 退出。
+#[derive(Debug)]
+pub struct DeviceOffline(String);
+
+impl fmt::Display for DeviceOffline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for DeviceOffline {}
+
+/// 在 `socket` 上监听并执行转发来的命令，直到按下 Ctrl-C。
+///
+/// 请求没有指定设备时使用 `default_device`。
+pub async fn serve(
+    xiaoai: &Xiaoai,
+    default_device: Option<String>,
+    socket: &Path,
+) -> anyhow::Result<()> {
+    // 套接字文件可能是上次异常退出留下的，但不能误删其他文件
+    if let Ok(metadata) = fs::symlink_metadata(socket) {
+        ensure!(
+            metadata.file_type().is_socket(),
+            "`{}` 已存在且不是套接字，请使用 --socket 指定其他路径",
+            socket.display()
+        );
+        if UnixStream::connect(socket).await.is_ok() {
+            bail!("已有守护进程在 `{}` 上运行", socket.display());
+        }
+        fs::remove_file(socket)
+            .with_context(|| format!("删除套接字 `{}` 失败", socket.display()))?;
+    }
+    let listener =
+        bind_private(socket).with_context(|| format!("监听套接字 `{}` 失败", socket.display()))?;
+    eprintln!("守护进程已在 `{}` 上运行，按 Ctrl-C 退出", socket.display());
+
+    let result = loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(err) => break Err(err.into()),
+            },
+            _ = tokio::signal::ctrl_c() => break Ok(()),
+        };
+        let xiaoai = xiaoai.clone();
+        let default_device = default_device.clone();
+        tokio::spawn(async move {
+            if let Err(err) = handle(&xiaoai, default_device.as_deref(), stream).await {
+                eprintln!("处理连接失败: {err}");
+            }
+        });
+    };
+    if fs::symlink_metadata(socket).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+        let _ = fs::remove_file(socket);
+    }
+
+    result
+}
+
+/// 在 `socket` 上监听，只允许当前用户连接。
+///
+/// 能连接的就能以当前账号执行命令。直接绑定后再修改权限会留下其他用户可以连接的间隙，
+/// 因此先在只有当前用户能进入的临时目录中绑定并修改权限，再移动到 `socket`。
+fn bind_private(socket: &Path) -> io::Result<UnixListener> {
+    let name = socket.file_name().unwrap_or_default().to_string_lossy();
+    let dir = socket.with_file_name(format!(".{name}.{}", std::process::id()));
+    fs::DirBuilder::new().mode(0o700).create(&dir)?;
+    let temp = dir.join("sock");
+    let result = UnixListener::bind(&temp).and_then(|listener| {
+        fs::set_permissions(&temp, fs::Permissions::from_mode(0o600))?;
+        fs::rename(&temp, socket)?;
+        Ok(listener)
+    });
+    let _ = fs::remove_file(&temp);
+    let _ = fs::remove_dir(&dir);
+
+    result
+}
+
+/// 转发请求到 `socket` 上的守护进程，返回应输出的内容。
+///
+/// 守护进程没有运行时返回 `None`，此时应直接执行命令。
+pub async fn forward(socket: &Path, request: &Value) -> anyhow::Result<Option<String>> {
+    let stream = match UnixStream::connect(socket).await {
+        Ok(stream) => stream,
+        Err(err)
+            if matches!(
+                err.kind(),
+                ErrorKind::NotFound | ErrorKind::ConnectionRefused
+            ) =>
+        {
+            return Ok(None);
+        }
+        Err(err) => return Err(err).context("连接守护进程失败"),
+    };
+    let (reader, mut writer) = stream.into_split();
+    writer.write_all(format!("{request}\n").as_bytes()).await?;
+    let line = BufReader::new(reader)
+        .lines()
+        .next_line()
+        .await?
+        .context("守护进程意外断开了连接")?;
+
+    let mut response: Value = serde_json::from_str(&line)?;
+    match (response["output"].take(), response["error"].take()) {
+        (Value::String(output), _) => Ok(Some(output)),
+        (_, Value::String(err)) if response["offline"] == true => Err(DeviceOffline(err).into()),
+        (_, Value::String(err)) => Err(anyhow!(err)),
+        _ => bail!("无法识别守护进程的响应: {line}"),
+    }
+}
+
+/// 处理一个连接上的所有请求。
+async fn handle(
+    xiaoai: &Xiaoai,
+    default_device: Option<&str>,
+    stream: UnixStream,
+) -> io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        let response = match execute(xiaoai, default_device, &line).await {
+            Ok(output) => json!({ "output": output }),
+            Err(err) => json!({
+                "error": format!("{err:#}"),
+                "offline": crate::is_device_offline(&err),
+            }),
+        };
+        writer.write_all(format!("{response}\n").as_bytes()).await?;
+    }
+
+    Ok(())
+}
+
+/// 执行一个请求，返回直接执行时输出的内容。
+async fn execute(
+    xiaoai: &Xiaoai,
+    default_device: Option<&str>,
+    request: &str,
+) -> anyhow::Result<String> {
+    let request: Value = serde_json::from_str(request).context("无效的请求")?;
+    let device_id = request["deviceId"]
+        .as_str()
+        .or(default_device)
+        .context("守护进程没有默认设备，请使用 --device-id 指定设备")?;
+    let json = request["json"].as_bool().unwrap_or_default();
+    let quiet = request["quiet"].as_bool().unwrap_or_default();
+    // 操作类命令的输出，同 `Cli::print_response`
+    let response = |value: Value| -> anyhow::Result<String> {
+        if quiet {
+            return Ok(String::new());
+        }
+        Ok(serde_json::to_string_pretty(&value)? + "\n")
+    };
+
+    match request["command"].as_str() {
+        Some("say") => {
+            let text = request["text"].as_str().context("缺少 text")?;
+            response(serde_json::to_value(xiaoai.tts(device_id, text).await?)?)
+        }
+        Some("volume") => match request["volume"].as_u64() {
+            Some(volume) => {
+                let volume = u32::try_from(volume)?;
+                response(serde_json::to_value(
+                    xiaoai.set_volume(device_id, volume).await?,
+                )?)
+            }
+            None => {
+                let volume = xiaoai.get_volume(device_id).await?;
+                if json {
+                    return Ok(serde_json::to_string_pretty(&json!({ "volume": volume }))? + "\n");
+                }
+                Ok(format!("{volume}\n"))
+            }
+        },
+        Some("status") => {
+            let status = xiaoai.player_status(device_id).await?;
+            if json {
+                return Ok(serde_json::to_string_pretty(&status)? + "\n");
+            }
+            Ok(DisplayPlayerStatus(&status).to_string())
+        }
+        command => bail!("守护进程不支持命令 {command:?}"),
+    }
+}
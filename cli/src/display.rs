@@ -0,0 +1,264 @@
+//! 命令输出的文本格式。
+//!
+//! 各命令以 `Display*` 包装库中的类型来输出给人看的文本，`--json` 时则直接序列化，不经过这里。
+
+use std::{
+    fmt::{self, Display, Formatter},
+    io::{self, IsTerminal},
+    time::Duration,
+};
+
+use miai::{DeviceInfo, PlaybackState, PlayerStatus, alarm::Alarm, dnd::Dnd};
+
+/// 标签的显示宽度，即两个汉字。
+const LABEL_WIDTH: usize = 4;
+
+/// 是否为输出加上颜色：标准输出是终端，且没有设置 `NO_COLOR` 环境变量。
+pub fn use_color() -> bool {
+    std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()) && io::stdout().is_terminal()
+}
+
+/// 文本在终端中的显示宽度，全角字符按两列计算。
+fn display_width(text: &str) -> usize {
+    text.chars().map(|c| if c.is_ascii() { 1 } else { 2 }).sum()
+}
+
+/// 输出一行 `标签: 值`，标签补齐到 [`LABEL_WIDTH`]，使各行的值对齐。
+fn field(f: &mut Formatter<'_>, label: &str, value: impl Display) -> fmt::Result {
+    let padding = LABEL_WIDTH.saturating_sub(display_width(label));
+    writeln!(f, "{label}: {:padding$}{value}", "")
+}
+
+/// 用 ANSI 转义序列为 `text` 加上样式，`enabled` 为 `false` 时原样输出。
+struct Paint<T> {
+    text: T,
+    style: &'static str,
+    enabled: bool,
+}
+
+impl<T: Display> Display for Paint<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.enabled {
+            write!(f, "\x1b[{}m{}\x1b[0m", self.style, self.text)
+        } else {
+            self.text.fmt(f)
+        }
+    }
+}
+
+const BOLD: &str = "1";
+const RED: &str = "31";
+const GREEN: &str = "32";
+
+/// 设备信息，默认为不带颜色的多行文本。
+pub struct DisplayDeviceInfo<'a> {
+    pub info: &'a DeviceInfo,
+    color: bool,
+    /// 显示为一行时名称补齐到的宽度，`None` 时显示为多行。
+    compact: Option<usize>,
+}
+
+impl<'a> DisplayDeviceInfo<'a> {
+    pub fn new(info: &'a DeviceInfo) -> Self {
+        Self {
+            info,
+            color: false,
+            compact: None,
+        }
+    }
+
+    /// 是否使用颜色，一般取 [`use_color`]。
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// 显示为一行，名称补齐到 `name_width`，用于在多个设备中选择时对齐。
+    pub fn compact(mut self, name_width: usize) -> Self {
+        self.compact = Some(name_width);
+        self
+    }
+
+    /// 一组设备中最宽的名称，用于 [`Self::compact`]。
+    pub fn name_width(devices: &[DeviceInfo]) -> usize {
+        devices
+            .iter()
+            .map(|info| display_width(&info.name))
+            .max()
+            .unwrap_or_default()
+    }
+
+    fn paint<T>(&self, text: T, style: &'static str) -> Paint<T> {
+        Paint {
+            text,
+            style,
+            enabled: self.color,
+        }
+    }
+
+    fn model(&self) -> String {
+        match self.info.model() {
+            Some(model) => format!("{}（{}）", model.name, self.info.hardware),
+            None => self.info.hardware.clone(),
+        }
+    }
+
+    fn status(&self) -> Paint<&'static str> {
+        if self.info.online {
+            self.paint("在线", GREEN)
+        } else {
+            self.paint("离线", RED)
+        }
+    }
+}
+
+impl Display for DisplayDeviceInfo<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let info = self.info;
+        if let Some(name_width) = self.compact {
+            let padding = name_width.saturating_sub(display_width(&info.name));
+            return write!(
+                f,
+                "{}{:padding$}  {}  {}",
+                self.paint(&info.name, BOLD),
+                "",
+                self.status(),
+                self.model()
+            );
+        }
+
+        field(f, "名称", self.paint(&info.name, BOLD))?;
+        field(f, "ID", &info.device_id)?;
+        if let Some(did) = &info.miio_did {
+            field(f, "DID", did)?;
+        }
+        field(f, "机型", self.model())?;
+        field(f, "状态", self.status())
+    }
+}
+
+pub struct DisplayPlayerStatus<'a>(pub &'a PlayerStatus);
+
+impl Display for DisplayPlayerStatus<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.0.state {
+            PlaybackState::Playing => writeln!(f, "状态: 播放中")?,
+            PlaybackState::Paused => writeln!(f, "状态: 已暂停")?,
+            PlaybackState::Buffering => writeln!(f, "状态: 缓冲中")?,
+            PlaybackState::Unknown(status) => writeln!(f, "状态: 未知（{status}）")?,
+            _ => writeln!(f, "状态: 已停止")?,
+        }
+        writeln!(f, "音量: {}", self.0.volume)?;
+        if let Some(title) = &self.0.title {
+            writeln!(f, "标题: {title}")?;
+        }
+        if let Some(artist) = &self.0.artist {
+            writeln!(f, "歌手: {artist}")?;
+        }
+        if let Some(album) = &self.0.album {
+            writeln!(f, "专辑: {album}")?;
+        }
+        if let Some(cover_url) = &self.0.cover_url {
+            writeln!(f, "封面: {cover_url}")?;
+        }
+        if let (Some(position), Some(duration)) = (self.0.position, self.0.duration) {
+            writeln!(f, "进度: {}/{}", mm_ss(position), mm_ss(duration))?;
+        }
+        if let Some(mode) = self.0.play_mode {
+            writeln!(f, "模式: {mode}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// 以 `mm:ss` 的格式显示时长，超过一小时时分钟数会大于 59。
+fn mm_ss(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+}
+
+pub struct DisplayAlarm<'a>(pub &'a Alarm);
+
+impl Display for DisplayAlarm<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let alarm = self.0;
+        if let Some(id) = &alarm.id {
+            writeln!(f, "ID:   {id}")?;
+        }
+        writeln!(
+            f,
+            "时间: {:02}:{:02}",
+            alarm.time.hour(),
+            alarm.time.minute()
+        )?;
+        writeln!(f, "重复: {}", alarm.repeat)?;
+        if !alarm.label.is_empty() {
+            writeln!(f, "标签: {}", alarm.label)?;
+        }
+        writeln!(f, "启用: {}", if alarm.enabled { "是" } else { "否" })
+    }
+}
+
+pub struct DisplayDnd<'a>(pub &'a Dnd);
+
+impl Display for DisplayDnd<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let dnd = self.0;
+        writeln!(f, "状态: {}", if dnd.enabled { "开启" } else { "关闭" })?;
+        if dnd.is_all_day() {
+            writeln!(f, "时间: 全天")
+        } else {
+            writeln!(
+                f,
+                "时间: {:02}:{:02} - {:02}:{:02}",
+                dnd.start.hour(),
+                dnd.start.minute(),
+                dnd.end.hour(),
+                dnd.end.minute()
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn devices() -> Vec<DeviceInfo> {
+        serde_json::from_value(json!([
+            {"deviceID": "a", "miioDID": 123, "name": "客厅", "hardware": "LX06", "presence": "online"},
+            {"deviceID": "b", "name": "Bedroom", "hardware": "X1", "presence": "offline"},
+        ]))
+        .unwrap()
+    }
+
+    #[test]
+    fn device_info_plain() {
+        let devices = devices();
+        assert_eq!(
+            DisplayDeviceInfo::new(&devices[0]).to_string(),
+            "名称: 客厅\nID:   a\nDID:  123\n机型: 小爱音箱 Pro（LX06）\n状态: 在线\n"
+        );
+        assert_eq!(
+            DisplayDeviceInfo::new(&devices[1]).to_string(),
+            "名称: Bedroom\nID:   b\n机型: X1\n状态: 离线\n"
+        );
+    }
+
+    #[test]
+    fn device_info_compact() {
+        let devices = devices();
+        let name_width = DisplayDeviceInfo::name_width(&devices);
+        let lines: Vec<_> = devices
+            .iter()
+            .map(|info| DisplayDeviceInfo::new(info).compact(name_width).to_string())
+            .collect();
+        assert_eq!(
+            lines,
+            ["客厅     在线  小爱音箱 Pro（LX06）", "Bedroom  离线  X1"]
+        );
+    }
+}
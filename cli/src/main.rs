@@ -1,41 +1,150 @@
+#[cfg(unix)]
+mod daemon;
+mod display;
+
 use std::{
+    borrow::Cow,
+    convert::Infallible,
     fmt::Display,
-    fs::File,
-    io::{self, BufReader},
-    mem::take,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+    num::ParseIntError,
     path::PathBuf,
+    pin::pin,
+    process::ExitCode,
+    str::FromStr,
+    time::{Duration, Instant},
 };
 
-use anyhow::{Context, anyhow, ensure};
-use clap::{Parser, Subcommand};
-use inquire::{Confirm, Password, PasswordDisplayMode, Select, Text};
-use miai::{DeviceInfo, PlayState, Xiaoai, conversation::AnswerPayload};
+use anyhow::{Context, anyhow, bail, ensure};
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use futures_util::{StreamExt, TryStreamExt, future};
+use inquire::{Confirm, InquireError, Password, PasswordDisplayMode, Select, Text};
+use miai::{
+    Capability, DEFAULT_RAMP_STEPS, DeviceInfo, MAX_VOLUME, NlpResult, PlayMode, PlayState, Region,
+    UbusResponse, Xiaoai, XiaoaiBuilder, XiaoaiResponse,
+    alarm::{self, Alarm, Repeat},
+    conversation::{Answer, AnswerPayload, Record},
+    login::VerifyMethod,
+    parse_duration,
+};
 use once_cell::unsync::OnceCell;
-use serde_json::Value;
-use time::{OffsetDateTime, UtcOffset};
+use qrcode::render::unicode::Dense1x2;
+use serde::Serialize;
+use serde_json::{Value, json};
+use time::{OffsetDateTime, Time, UtcOffset, format_description::well_known::Rfc3339};
 use tracing_subscriber::EnvFilter;
 use url::Url;
 
+use crate::display::{DisplayAlarm, DisplayDeviceInfo, DisplayDnd, DisplayPlayerStatus, use_color};
+
 const DEFAULT_AUTH_FILE: &str = "xiaoai-auth.json";
+/// 获取对话记录时每页的条数。
+const HISTORY_PAGE_SIZE: u32 = 50;
+/// 等待小爱回答的最长时间。
+const ASK_WAIT_TIMEOUT: Duration = Duration::from_secs(30);
+/// 播放本地文件时，最多提供文件服务的时长。
+const PLAY_FILE_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+/// 依次播放多个链接时，每首最多等待的时长。
+const PLAY_TRACK_TIMEOUT: Duration = Duration::from_secs(20 * 60);
+
+/// 设备离线时的退出码，便于脚本区分并稍后重试，取自 sysexits 的 `EX_UNAVAILABLE`。
+const EXIT_DEVICE_OFFLINE: u8 = 69;
+/// 设备列表缓存超过这个时长时提醒更新。
+const DEVICE_CACHE_MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
 
 #[tokio::main(flavor = "current_thread")]
-async fn main() -> anyhow::Result<()> {
+async fn main() -> ExitCode {
     // 初始化日志
     tracing_subscriber::fmt()
         .with_writer(io::stderr)
         .with_env_filter(EnvFilter::from_default_env())
         .init();
-    let cli = Cli::parse();
-
-    if let Commands::Login = cli.command {
-        let username = Text::new("账号:").prompt()?;
-        let password = Password::new("密码:")
-            .with_display_toggle_enabled()
-            .with_display_mode(PasswordDisplayMode::Masked)
-            .without_confirmation()
-            .with_help_message("CTRL + R 显示/隐藏密码")
-            .prompt()?;
-        let xiaoai = Xiaoai::login(&username, &password).await?;
+
+    match run(&mut Cli::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {err:?}");
+            if is_device_offline(&err) {
+                ExitCode::from(EXIT_DEVICE_OFFLINE)
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+    }
+}
+
+async fn run(cli: &mut Cli) -> anyhow::Result<()> {
+    if let Commands::Completions { shell } = cli.command {
+        let mut command = Cli::command();
+        clap_complete::generate(
+            shell,
+            &mut command,
+            env!("CARGO_BIN_NAME"),
+            &mut io::stdout(),
+        );
+        return Ok(());
+    }
+
+    // 标准输入只能读取一次，在同时操作多个设备前先读出来
+    if let Commands::Say { text, file, .. } = &mut cli.command {
+        *text = Some(read_say_text(text.take(), file.take())?);
+    }
+
+    if let Commands::Login {
+        qr,
+        username,
+        password,
+    } = &cli.command
+    {
+        let builder = cli.builder();
+        let xiaoai = if *qr {
+            builder
+                .login_qr(|qr_code| {
+                    // 二维码内容过长时无法渲染，只输出链接
+                    if let Ok(code) = qrcode::QrCode::new(&qr_code.login_url) {
+                        let image = code
+                            .render::<Dense1x2>()
+                            .dark_color(Dense1x2::Light)
+                            .light_color(Dense1x2::Dark)
+                            .build();
+                        eprintln!("{image}");
+                    }
+                    eprintln!(
+                        "请使用米家 App 扫描二维码并确认登录，二维码图片: {}",
+                        qr_code.image_url
+                    );
+                    eprintln!("二维码将在 {} 秒后过期", qr_code.timeout);
+                })
+                .await?
+        } else {
+            // 通过参数或环境变量指定时跳过提示
+            let username = match username {
+                Some(username) => username.clone(),
+                None => Text::new("账号:").prompt()?,
+            };
+            let password = match password {
+                Some(password) => password.clone(),
+                None => Password::new("密码:")
+                    .with_display_toggle_enabled()
+                    .with_display_mode(PasswordDisplayMode::Masked)
+                    .without_confirmation()
+                    .with_help_message("CTRL + R 显示/隐藏密码")
+                    .prompt()?,
+            };
+            builder
+                .login_with_verification(&username, &password, |verification| {
+                    let target = match verification.method {
+                        VerifyMethod::Phone => "手机",
+                        VerifyMethod::Email => "邮箱",
+                    };
+                    Text::new(&format!("验证码已发送至绑定的{target}，请输入:"))
+                        .prompt()
+                        .ok()
+                })
+                .await?
+        };
 
         let can_save = if cli.auth_file.exists() {
             Confirm::new(&format!("{} 已存在，是否覆盖?", cli.auth_file.display())).prompt()?
@@ -43,93 +152,851 @@ async fn main() -> anyhow::Result<()> {
             true
         };
 
+        if let Some(user_id) = xiaoai.user_id() {
+            println!("已登录账号 {user_id}");
+        }
         if can_save {
-            let mut file = File::create(cli.auth_file)?;
-            xiaoai.save(&mut file).map_err(anyhow::Error::from_boxed)?;
+            let mut file = File::create(&cli.auth_file)?;
+            xiaoai.save(&mut file)?;
+        }
+        return Ok(());
+    }
+
+    // 读取缓存的设备列表不需要登录
+    if let Commands::Device { cached: true } = cli.command {
+        let path = cli.device_cache_file();
+        let cached = Xiaoai::device_info_cached(&path).with_context(|| {
+            format!(
+                "读取设备列表缓存 `{}` 失败，请先运行 `xiaoai device` 获取",
+                path.display()
+            )
+        })?;
+        let age = cached.age();
+        eprintln!("设备列表缓存于 {} 前", format_age(age));
+        if age > DEVICE_CACHE_MAX_AGE {
+            eprintln!("警告: 缓存已超过 7 天，可能已经过时，请运行 `xiaoai device` 更新");
         }
+        return print_device_info(cli, &cached.devices);
+    }
+
+    // 守护进程在运行时转发给它执行，已经加载过（比如在交互模式中）时直接执行
+    #[cfg(unix)]
+    if cli.xiaoai.get().is_none()
+        && let Some(request) = cli.daemon_request()
+        && let Some(output) = daemon::forward(&cli.socket(), &request).await?
+    {
+        print!("{output}");
         return Ok(());
     }
 
     // 之后的命令需要登录
     let xiaoai = cli.xiaoai()?;
-    if let Commands::Device = cli.command {
-        let device_info = cli.device_info().await?;
-        for (i, info) in device_info.iter().enumerate() {
+    ensure!(
+        !cli.dry_run || cli.command.supports_dry_run(),
+        "该命令不支持 --dry-run"
+    );
+    if let Commands::Logout = cli.command {
+        if let Err(err) = xiaoai.clone().logout().await {
+            eprintln!("警告: 注销请求失败，服务器上的登录状态可能仍然有效: {err}");
+        }
+        fs::remove_file(&cli.auth_file)
+            .with_context(|| format!("删除认证文件 `{}` 失败", cli.auth_file.display()))?;
+        return Ok(());
+    }
+    if let Commands::Repl = cli.command {
+        return repl(cli).await;
+    }
+    if let Commands::Daemon = cli.command {
+        #[cfg(not(unix))]
+        bail!("守护进程模式只支持 Unix 系统");
+        #[cfg(unix)]
+        {
+            // 指定了设备时作为默认设备，否则只有一个设备时默认使用它
+            let specified = cli.all
+                || !cli.device_id.is_empty()
+                || cli.device_name.is_some()
+                || cli.device_index.is_some();
+            let default_device = if specified {
+                Some(cli.device_id().await?.to_string())
+            } else {
+                match &cli.device_info().await?[..] {
+                    [info] => Some(info.device_id.clone()),
+                    _ => None,
+                }
+            };
+            return daemon::serve(xiaoai, default_device, &cli.socket()).await;
+        }
+    }
+    if let Commands::Whoami = cli.command {
+        let user_id = xiaoai
+            .user_id()
+            .with_context(|| format!("认证文件 `{}` 中没有账号信息", cli.auth_file.display()))?;
+        if cli.json {
+            return print_json(&json!({ "userId": user_id }));
+        }
+        println!("{user_id}");
+        return Ok(());
+    }
+    if let Commands::Device { .. } = cli.command {
+        let path = cli.device_cache_file();
+        let device_info = cli
+            .device_info
+            .get_or_try_init(async || {
+                xiaoai
+                    .cache_device_info(&path)
+                    .await
+                    .context("获取设备列表失败")
+            })
+            .await?;
+        return print_device_info(cli, device_info);
+    }
+
+    // 之后的命令需要设备 ID
+    if let Commands::History {
+        limit,
+        kinds,
+        csv,
+        reverse,
+        prefer,
+        ndjson,
+        since,
+        until,
+        utc,
+        tz,
+    } = &cli.command
+    {
+        // 默认使用加载时设置的本地时区
+        let offset = if *utc { Some(UtcOffset::UTC) } else { *tz };
+        // 指定了起始时间时，默认取出范围内的所有记录
+        let limit = limit.unwrap_or(if since.is_some() { u32::MAX } else { 1 });
+        let device_id = cli.device_id().await?;
+        let info = xiaoai.device_info_one(device_id).await?;
+        let records = xiaoai
+            .conversations_between(
+                device_id,
+                &info.hardware,
+                since.unwrap_or(OffsetDateTime::UNIX_EPOCH),
+                until.unwrap_or_else(OffsetDateTime::now_utc),
+                limit.min(HISTORY_PAGE_SIZE),
+            )
+            // 先过滤再计数，不足时会继续翻页
+            .try_filter(|record| {
+                let matched = kinds.is_empty() || kinds.iter().any(|kind| record.has_kind(kind));
+                future::ready(matched)
+            })
+            .take(limit as usize)
+            .map_ok(|record| match offset {
+                Some(offset) => record.to_offset(offset),
+                None => record,
+            });
+        if *ndjson {
+            // 每获取到一条就输出一行，标准输出按行缓冲，每行都会立即写出
+            let mut records = pin!(records);
+            while let Some(record) = records.try_next().await? {
+                println!("{}", serde_json::to_string(&record)?);
+            }
+            return Ok(());
+        }
+        let mut records: Vec<Record> = records.try_collect().await?;
+        if *reverse {
+            // 在取满条数后再排序，时间相同时按请求 ID 排序以保证输出稳定
+            records.sort_by(|a, b| (a.time, &a.request_id).cmp(&(b.time, &b.request_id)));
+        }
+        if let Some(path) = csv {
+            let file = File::create(path)
+                .with_context(|| format!("创建文件 `{}` 失败", path.display()))?;
+            return write_csv(BufWriter::new(file), &records, prefer);
+        }
+        if cli.json {
+            return print_json(&records);
+        }
+        if cli.quiet {
+            // 每行一条记录，提问和应答以制表符分隔
+            for record in &records {
+                let answer = record.primary_answer(prefer).and_then(Answer::display_text);
+                println!("{}\t{}", record.query, answer.unwrap_or_default());
+            }
+            return Ok(());
+        }
+        for (i, record) in records.iter().enumerate() {
             if i != 0 {
                 println!();
             }
-            print!("{}", DisplayDeviceInfo(info));
+            print_record(record);
         }
         return Ok(());
     }
 
-    // 之后的命令需要设备 ID
-    let device_id = cli.device_id().await?;
-    if let Commands::History { limit } = cli.command {
-        let info = cli
-            .device_info()
-            .await?
-            .iter()
-            .find(|x| x.device_id == device_id)
-            .ok_or_else(|| anyhow!("找不到设备 `{device_id}` 的信息"))?;
-        let mut records = xiaoai
-            .conversations(device_id, &info.hardware, OffsetDateTime::now_utc(), limit)
-            .await?
-            .records;
-        // 尝试换算成本地时间偏移
-        if let Ok(offset) = UtcOffset::current_local_offset() {
-            for record in &mut records {
-                record.time = record.time.to_offset(offset);
-            }
-        }
-        for (i, mut record) in records.into_iter().enumerate() {
-            if i != 0 {
+    if let Commands::Watch { interval } = &cli.command {
+        let device_id = cli.device_id().await?;
+        let records = xiaoai.watch_conversations(device_id, *interval);
+        let mut records = pin!(records);
+        let mut first = true;
+        while let Some(record) = records.next().await {
+            let record = match record {
+                Ok(record) => record,
+                // 偶尔的网络错误不应打断监听
+                Err(err) => {
+                    eprintln!("警告: 查询对话记录失败: {err}");
+                    continue;
+                }
+            };
+            if cli.json {
+                // 每行一条记录，方便流式处理
+                println!("{}", serde_json::to_string(&record)?);
+                continue;
+            }
+            if !first {
                 println!();
             }
-            println!("提问: {}", record.query);
-            // 目前只解析第一个应答
-            if let Some(answer) = record.answers.first_mut() {
-                print!("应答: ");
-                match &mut answer.payload {
-                    AnswerPayload::Tts { text, .. } => println!("{text}"),
-                    AnswerPayload::Llm { text, .. } => println!("{text}"),
-                    AnswerPayload::Unknown(payload) => println!("{}", Value::Object(take(payload))),
-                    _ => println!(),
+            first = false;
+            print_record(&record);
+        }
+        return Ok(());
+    }
+
+    if let Commands::Ask {
+        text, wait: true, ..
+    } = &cli.command
+    {
+        let device_id = cli.device_id().await?;
+        let record = xiaoai
+            .ask_and_wait(device_id, text, ASK_WAIT_TIMEOUT)
+            .await?;
+        if cli.json {
+            return print_json(&record);
+        }
+        for answer in &record.answers {
+            if let Some(text) = answer.display_text() {
+                println!("{text}");
+            }
+        }
+        return Ok(());
+    }
+
+    if let Commands::Music { query } = &cli.command {
+        let device_id = cli.device_id().await?;
+        let record = xiaoai
+            .play_music_search(device_id, &query.join(" "))
+            .await?;
+        if cli.quiet {
+            return Ok(());
+        }
+        if cli.json {
+            return print_json(&record);
+        }
+        let Some(record) = record else {
+            println!("已请求播放，但没有等到小爱的应答");
+            return Ok(());
+        };
+        for answer in &record.answers {
+            if let Some(text) = answer.display_text() {
+                println!("{text}");
+            }
+        }
+        return Ok(());
+    }
+
+    if let Commands::Rename { name } = &cli.command {
+        let device_id = cli.device_id().await?.to_string();
+        let response = xiaoai.rename_device(&device_id, name).await?;
+        // 名称已变，缓存的设备列表不再可用
+        cli.device_info.take();
+        return cli.print_response(&response);
+    }
+
+    if let Commands::Status = cli.command {
+        let device_id = cli.device_id().await?;
+        let status = xiaoai.player_status(device_id).await?;
+        if cli.json {
+            return print_json(&status);
+        }
+        print!("{}", DisplayPlayerStatus(&status));
+        return Ok(());
+    }
+
+    if let Commands::Alarm { command } = &cli.command {
+        let device_id = cli.device_id().await?;
+        let response = match command {
+            AlarmCommands::List => {
+                let alarms = xiaoai.list_alarms(device_id).await?;
+                if cli.json {
+                    return print_json(&alarms);
+                }
+                for (i, alarm) in alarms.iter().enumerate() {
+                    if i != 0 {
+                        println!();
+                    }
+                    print!("{}", DisplayAlarm(alarm));
+                }
+                return Ok(());
+            }
+            AlarmCommands::Add {
+                time,
+                repeat,
+                label,
+            } => {
+                let mut alarm = Alarm::new(*time, repeat.clone());
+                alarm.label = label.clone().unwrap_or_default();
+                xiaoai.create_alarm(device_id, &alarm).await?
+            }
+            AlarmCommands::Delete { id } => xiaoai.delete_alarm(device_id, id).await?,
+        };
+        return cli.print_response(&response);
+    }
+
+    if let Commands::Dnd { command } = &cli.command {
+        let device_id = cli.device_id().await?;
+        let response = match command {
+            DndCommands::On { start, end } => xiaoai.set_dnd(device_id, true, *start, *end).await?,
+            DndCommands::Off => {
+                // 保留原有的时间段
+                let dnd = xiaoai.get_dnd(device_id).await?;
+                xiaoai
+                    .set_dnd(device_id, false, Some(dnd.start), Some(dnd.end))
+                    .await?
+            }
+            DndCommands::Status => {
+                let dnd = xiaoai.get_dnd(device_id).await?;
+                if cli.json {
+                    return print_json(&dnd);
                 }
-                println!("类型: {}", answer.kind);
+                print!("{}", DisplayDnd(&dnd));
+                return Ok(());
             }
-            println!("ID:   {}", record.request_id);
-            println!("时间: {}", record.time);
+        };
+        return cli.print_response(&response);
+    }
+
+    if let Commands::Sleep { duration, cancel } = &cli.command {
+        let device_id = cli.device_id().await?;
+        if *cancel {
+            return cli.print_response(&xiaoai.cancel_sleep_timer(device_id).await?);
         }
+        let duration = duration.context("需要指定时长，或使用 --cancel 取消")?;
+        let end = xiaoai.set_sleep_timer(device_id, duration).await?;
+        if cli.quiet {
+            return Ok(());
+        }
+        if cli.json {
+            return print_json(&json!({ "end": end.unix_timestamp() }));
+        }
+        println!("将在 {end} 停止播放");
         return Ok(());
     }
 
-    // 处理剩下的命令
-    let response = match &cli.command {
-        Commands::Say { text } => xiaoai.tts(device_id, text).await?,
-        Commands::Play { url } => {
-            if let Some(url) = url {
-                xiaoai.play_url(device_id, url.as_str()).await?
-            } else {
-                xiaoai.set_play_state(device_id, PlayState::Play).await?
+    if let Commands::Mode { mode } = cli.command {
+        let device_id = cli.device_id().await?;
+        let mode = match mode {
+            Some(mode) => {
+                xiaoai.set_play_mode(device_id, mode).await?;
+                if cli.quiet {
+                    return Ok(());
+                }
+                mode
+            }
+            None => xiaoai.play_mode(device_id).await?,
+        };
+        if cli.json {
+            return print_json(&json!({ "mode": mode }));
+        }
+        println!("{mode}");
+        return Ok(());
+    }
+
+    if let Commands::Mute | Commands::Unmute = cli.command {
+        let device_id = cli.device_id().await?;
+        let volume = if let Commands::Mute = cli.command {
+            xiaoai.mute(device_id).await?
+        } else {
+            xiaoai.unmute(device_id).await?
+        };
+        if cli.quiet {
+            return Ok(());
+        }
+        if cli.json {
+            return print_json(&json!({ "volume": volume }));
+        }
+        println!("{volume}");
+        return Ok(());
+    }
+
+    if let Commands::Volume { volume: None, .. } = cli.command {
+        let device_id = cli.device_id().await?;
+        let volume = xiaoai.get_volume(device_id).await?;
+        if cli.json {
+            return print_json(&json!({ "volume": volume }));
+        }
+        println!("{volume}");
+        return Ok(());
+    }
+
+    // 处理剩下的命令，可以同时发往多个设备
+    let device_ids = cli.device_ids().await?;
+    if let Some(capability) = cli.command.capability() {
+        cli.warn_unsupported(&device_ids, capability);
+    }
+    let xiaoai = xiaoai.clone().with_dry_run(cli.dry_run);
+    let results = xiaoai.broadcast(&device_ids, |xiaoai, device_id| {
+        execute(xiaoai, device_id, &cli.command)
+    });
+    let mut results = if cli.stop_on_interrupt && cli.command.is_playback() && !cli.dry_run {
+        tokio::select! {
+            results = results => results,
+            _ = tokio::signal::ctrl_c() => {
+                eprintln!("已中断，正在停止播放");
+                for (device_id, result) in xiaoai.broadcast(&device_ids, Xiaoai::stop_all).await {
+                    if let Err(err) = result {
+                        eprintln!("{device_id}: 停止播放失败: {err}");
+                    }
+                }
+                bail!("已中断");
+            }
+        }
+    } else {
+        results.await
+    };
+    // 只有一个设备时，保持原样输出
+    if results.len() == 1 {
+        let (_, result) = results.remove(0);
+        let response = result?;
+        if let Commands::Ask { .. } = cli.command
+            && !cli.json
+            && !cli.quiet
+            && !cli.dry_run
+        {
+            let result =
+                NlpResult::from(serde_json::from_value::<XiaoaiResponse>(response.clone())?);
+            if result.is_recognized() {
+                return print_nlp_result(&result);
+            }
+        }
+        return cli.print_response(&response);
+    }
+
+    let mut failed = 0;
+    let mut summary = Vec::with_capacity(results.len());
+    for (device_id, result) in results {
+        match result {
+            Ok(response) if cli.json => {
+                summary.push(json!({ "deviceID": device_id, "response": response }))
+            }
+            Ok(_) if cli.quiet => (),
+            Ok(_) => println!("{device_id}: 成功"),
+            Err(err) => {
+                failed += 1;
+                if cli.json {
+                    summary.push(json!({ "deviceID": device_id, "error": err.to_string() }));
+                } else {
+                    eprintln!("{device_id}: 失败: {err}");
+                }
+            }
+        }
+    }
+    if cli.json && !cli.quiet {
+        print_json(&summary)?;
+    }
+    ensure!(failed == 0, "{failed} 个设备执行失败");
+
+    Ok(())
+}
+
+/// 交互式地执行命令，所有命令共享同一个登录状态和设备列表。
+async fn repl(cli: &mut Cli) -> anyhow::Result<()> {
+    // 先确定目标设备，之后的命令无需再次选择
+    if !cli.all && cli.device_id.len() <= 1 {
+        let device_id = cli.device_id().await?.to_string();
+        cli.device_id = vec![device_id];
+        cli.device_name = None;
+        cli.device_index = None;
+    }
+
+    eprintln!("输入命令，如 `say 你好`、`volume 30`，输入 quit 或 exit 退出");
+    loop {
+        let line = match Text::new("xiaoai>").prompt() {
+            Ok(line) => line,
+            Err(InquireError::OperationCanceled | InquireError::OperationInterrupted) => break,
+            Err(err) => return Err(err.into()),
+        };
+        let args = match split_args(&line) {
+            Ok(args) => args,
+            Err(err) => {
+                eprintln!("Error: {err}");
+                continue;
+            }
+        };
+        match args.first().map(String::as_str) {
+            None => continue,
+            Some("quit" | "exit") => break,
+            Some(_) => (),
+        }
+        let command = match ReplLine::try_parse_from(&args) {
+            Ok(line) => line.command,
+            Err(err) => {
+                let _ = err.print();
+                continue;
+            }
+        };
+        if let Commands::Login { .. }
+        | Commands::Logout
+        | Commands::Repl
+        | Commands::Daemon
+        | Commands::Completions { .. } = command
+        {
+            eprintln!("Error: 交互模式中不能使用该命令");
+            continue;
+        }
+
+        cli.command = command;
+        if let Err(err) = Box::pin(run(cli)).await {
+            eprintln!("Error: {err:?}");
+        }
+    }
+
+    Ok(())
+}
+
+/// 交互模式中的一行命令。
+#[derive(Parser)]
+#[command(no_binary_name = true)]
+struct ReplLine {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+/// 按空白拆分参数，单引号或双引号中的空白不拆分。
+fn split_args(line: &str) -> anyhow::Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut arg: Option<String> = None;
+    let mut quote = None;
+    for c in line.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => arg.get_or_insert_default().push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                arg.get_or_insert_default();
+            }
+            (None, c) if c.is_whitespace() => args.extend(arg.take()),
+            (None, c) => arg.get_or_insert_default().push(c),
+        }
+    }
+    ensure!(quote.is_none(), "引号没有闭合");
+    args.extend(arg);
+
+    Ok(args)
+}
+
+/// 对单个设备执行剩下的命令。
+async fn execute(xiaoai: &Xiaoai, device_id: &str, command: &Commands) -> miai::Result<Value> {
+    let response = match command {
+        Commands::Say {
+            text: Some(text),
+            chunked: true,
+            ..
+        } => {
+            let responses = xiaoai.tts_chunked(device_id, text).await?;
+            return Ok(serde_json::to_value(responses)?);
+        }
+        Commands::Say {
+            text: Some(text), ..
+        } => xiaoai.tts(device_id, text).await?,
+        Commands::Play { sources } => match sources.as_slice() {
+            [PlaySource::Url(url)] => xiaoai.play_url(device_id, url.as_str()).await?,
+            [PlaySource::File(path)] => {
+                xiaoai.play_file(device_id, path, PLAY_FILE_TIMEOUT).await?
             }
+            [] => return set_play_state(xiaoai, device_id, PlayState::Play).await,
+            sources => {
+                // 多个时只支持链接，本地文件会被跳过
+                let urls: Vec<String> = sources.iter().map(ToString::to_string).collect();
+                let urls: Vec<&str> = urls.iter().map(String::as_str).collect();
+                let results = xiaoai
+                    .play_urls(device_id, &urls, PLAY_TRACK_TIMEOUT)
+                    .await?;
+                let mut accepted = 0;
+                for (url, result) in results {
+                    match result {
+                        Ok(_) => accepted += 1,
+                        Err(err) => eprintln!("警告: {device_id}: 跳过 `{url}`: {err}"),
+                    }
+                }
+                if accepted == 0 {
+                    return Err(miai::Error::InvalidInput("没有可播放的链接".into()));
+                }
+                return Ok(json!({ "accepted": accepted, "total": urls.len() }));
+            }
+        },
+        Commands::Volume {
+            volume: Some(VolumeArg::Absolute(volume)),
+            ramp: Some(over),
+            ..
+        } => {
+            let volume = xiaoai
+                .ramp_volume(device_id, *volume, *over, DEFAULT_RAMP_STEPS)
+                .await?;
+            return Ok(json!({ "volume": volume }));
+        }
+        Commands::Volume {
+            volume: Some(VolumeArg::Relative(_)),
+            ramp: Some(_),
+            ..
+        } => {
+            return Err(miai::Error::InvalidInput(
+                "--ramp 只对绝对的音量有效".into(),
+            ));
+        }
+        Commands::Volume {
+            volume: Some(VolumeArg::Absolute(volume)),
+            verify: true,
+            ..
+        } => xiaoai.set_volume_verified(device_id, *volume).await?,
+        Commands::Volume {
+            volume: Some(VolumeArg::Absolute(volume)),
+            ..
+        } => xiaoai.set_volume(device_id, *volume).await?,
+        Commands::Volume {
+            volume: Some(VolumeArg::Relative(delta)),
+            ..
+        } => {
+            let volume = xiaoai.adjust_volume(device_id, *delta).await?;
+            return Ok(json!({ "volume": volume }));
+        }
+        Commands::Ask {
+            text, silent: true, ..
+        } => xiaoai.execute_silent(device_id, text).await?,
+        Commands::Ask { text, .. } => xiaoai.nlp(device_id, text).await?,
+        Commands::Pause => return set_play_state(xiaoai, device_id, PlayState::Pause).await,
+        Commands::Stop => xiaoai.stop_all(device_id).await?,
+        Commands::Next => xiaoai.next_track(device_id).await?,
+        Commands::Prev => xiaoai.prev_track(device_id).await?,
+        Commands::Ping => {
+            let start = Instant::now();
+            xiaoai.ping(device_id).await?;
+            return Ok(json!({ "ok": true, "latencyMs": start.elapsed().as_millis() }));
         }
-        Commands::Volume { volume } => xiaoai.set_volume(device_id, *volume).await?,
-        Commands::Ask { text } => xiaoai.nlp(device_id, text).await?,
-        Commands::Pause => xiaoai.set_play_state(device_id, PlayState::Pause).await?,
-        Commands::Stop => xiaoai.set_play_state(device_id, PlayState::Stop).await?,
         Commands::Ubus {
             path,
             method,
             message,
-        } => xiaoai.ubus_call(device_id, path, method, message).await?,
+            info,
+        } => {
+            let response = xiaoai.ubus_call(device_id, path, method, message).await?;
+            if *info {
+                return UbusResponse::from(response).parse();
+            }
+            response
+        }
         cmd => unreachable!("命令 `{:?}` 应该被处理", cmd),
     };
-    println!("{}", serde_json::to_string_pretty(&response)?);
+
+    Ok(serde_json::to_value(response)?)
+}
+
+/// 校验参数是有效的 JSON，在加载认证文件前就指出错误。
+fn parse_json(s: &str) -> Result<String, String> {
+    match serde_json::from_str::<serde_json::Value>(s) {
+        Ok(_) => Ok(s.to_owned()),
+        Err(err) => Err(format!("无效的 JSON: {err}")),
+    }
+}
+
+/// 解析 RFC 3339 格式的时间，或距今的时长，如 `2d` 表示两天前。
+fn parse_time(s: &str) -> anyhow::Result<OffsetDateTime> {
+    if let Ok(time) = OffsetDateTime::parse(s.trim(), &Rfc3339) {
+        return Ok(time);
+    }
+    let ago = parse_duration(s).with_context(|| format!("无效的时间 `{s}`"))?;
+
+    time::Duration::try_from(ago)
+        .ok()
+        .and_then(|ago| OffsetDateTime::now_utc().checked_sub(ago))
+        .with_context(|| format!("时间 `{s}` 超出了可以表示的范围"))
+}
+
+/// 是否是因为设备离线而失败，包括守护进程转发回来的错误。
+fn is_device_offline(err: &anyhow::Error) -> bool {
+    err.chain().any(|err| {
+        #[cfg(unix)]
+        if err.is::<daemon::DeviceOffline>() {
+            return true;
+        }
+        err.downcast_ref::<miai::Error>()
+            .is_some_and(miai::Error::is_device_offline)
+    })
+}
+
+/// 设置播放状态，确认设备接受了操作，演练时则返回将要发送的请求。
+async fn set_play_state(xiaoai: &Xiaoai, device_id: &str, state: PlayState) -> miai::Result<Value> {
+    if xiaoai.is_dry_run() {
+        return Ok(serde_json::to_value(
+            xiaoai.set_play_state(device_id, state).await?,
+        )?);
+    }
+    xiaoai.set_play_state_checked(device_id, state).await?;
+
+    Ok(json!({ "ok": true }))
+}
+
+/// 输出识别出的意图和答复，没有的部分省略。
+fn print_nlp_result(result: &NlpResult) -> anyhow::Result<()> {
+    let parts: Vec<_> = [("意图", &result.intent), ("答复", &result.answer)]
+        .into_iter()
+        .filter_map(|(label, value)| Some(format!("{label}: {}", value.as_deref()?)))
+        .collect();
+    println!("{}", parts.join(" / "));
+
+    Ok(())
+}
+
+/// 解析 `+08:00`、`-05:00` 这样的时区偏移，分钟可以省略。
+fn parse_offset(s: &str) -> Result<UtcOffset, String> {
+    let invalid = || format!("无效的时区偏移 `{s}`，应为 +08:00 这样的格式");
+    let s = s.trim();
+    let (sign, rest) = match s.split_at_checked(1) {
+        Some(("+", rest)) => (1, rest),
+        Some(("-", rest)) => (-1, rest),
+        _ => return Err(invalid()),
+    };
+    let (hours, minutes) = rest.split_once(':').unwrap_or((rest, "0"));
+    let parse = |part: &str| {
+        (!part.is_empty() && part.len() <= 2 && part.bytes().all(|b| b.is_ascii_digit()))
+            .then(|| part.parse::<i8>().ok())
+            .flatten()
+            .ok_or_else(invalid)
+    };
+    let (hours, minutes) = (parse(hours)?, parse(minutes)?);
+
+    UtcOffset::from_hms(sign * hours, sign * minutes, 0).map_err(|_| invalid())
+}
+
+/// 以易读的格式输出一条对话记录。
+fn print_record(record: &Record) {
+    println!("提问: {}", record.query);
+    // 按原有顺序列出所有应答
+    for answer in &record.answers {
+        println!("应答: {}", render_answer(answer));
+        println!("类型: {}", answer.kind);
+    }
+    println!("ID:   {}", record.request_id);
+    println!("时间: {}", record.time);
+}
+
+/// 按应答的类型渲染为一行文本。
+///
+/// - TTS、LLM 等有文本的应答直接输出文本；
+/// - 音乐输出为“▶ 标题 - 歌手”；
+/// - 未知的类型没有可读的文本时，以 `键: 值` 的形式列出所有字段。
+fn render_answer(answer: &Answer) -> String {
+    const NO_TEXT: &str = "（无文本）";
+    let text = answer.display_text();
+    match &answer.payload {
+        AnswerPayload::Music { .. } => format!("▶ {}", text.as_deref().unwrap_or("未知歌曲")),
+        AnswerPayload::Unknown(payload) if text.is_none() && !payload.is_empty() => payload
+            .iter()
+            .map(|(key, value)| match value {
+                Value::String(value) => format!("{key}: {value}"),
+                value => format!("{key}: {value}"),
+            })
+            .collect::<Vec<_>>()
+            .join(", "),
+        _ => text.map_or_else(|| NO_TEXT.to_string(), Cow::into_owned),
+    }
+}
+
+/// 以 JSON 格式输出到标准输出。
+fn print_json(value: &impl Serialize) -> anyhow::Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+
+    Ok(())
+}
+
+/// 读取 `say` 要播报的文本，`text` 为 `-` 时从标准输入读取，否则从 `file` 读取。
+///
+/// 去掉末尾的换行，保留其余的格式。
+fn read_say_text(text: Option<String>, file: Option<PathBuf>) -> anyhow::Result<String> {
+    let mut text = match (text, file) {
+        (Some(text), _) if text == "-" => {
+            io::read_to_string(io::stdin()).context("从标准输入读取文本失败")?
+        }
+        (Some(text), _) => return Ok(text),
+        (None, Some(file)) => fs::read_to_string(&file)
+            .with_context(|| format!("读取文件 `{}` 失败", file.display()))?,
+        (None, None) => bail!("需要指定要播报的文本或 --file"),
+    };
+    text.truncate(text.trim_end_matches(['\r', '\n']).len());
+    ensure!(!text.trim().is_empty(), "要播报的文本为空");
+
+    Ok(text)
+}
+
+/// 按 `--json` 和 `--quiet` 输出设备列表。
+fn print_device_info(cli: &Cli, device_info: &[DeviceInfo]) -> anyhow::Result<()> {
+    if cli.json {
+        return print_json(&device_info);
+    }
+    if cli.quiet {
+        for info in device_info {
+            println!("{}", info.device_id);
+        }
+        return Ok(());
+    }
+    let color = use_color();
+    for (i, info) in device_info.iter().enumerate() {
+        if i != 0 {
+            println!();
+        }
+        print!("{}", DisplayDeviceInfo::new(info).color(color));
+    }
+
+    Ok(())
+}
+
+/// 以最大的单位粗略地显示时长，如 `3 天`。
+fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    match secs {
+        0..60 => format!("{secs} 秒"),
+        60..3600 => format!("{} 分钟", secs / 60),
+        3600..86400 => format!("{} 小时", secs / 3600),
+        _ => format!("{} 天", secs / 86400),
+    }
+}
+
+/// 以 RFC 4180 的 CSV 格式写出对话记录，每条记录一行。
+///
+/// 应答取 [`Record::primary_answer`]，没有时留空。
+fn write_csv(mut writer: impl Write, records: &[Record], prefer: &[String]) -> anyhow::Result<()> {
+    writer.write_all(b"query,answer,type,request_id,time\r\n")?;
+    for record in records {
+        let answer = record
+            .primary_answer(prefer)
+            .and_then(|answer| Some((answer.display_text()?, answer.kind.as_str())));
+        let (text, kind) = answer.unwrap_or_default();
+        let time = record.time.format(&Rfc3339)?;
+        let fields = [&record.query, &*text, kind, &record.request_id, &time];
+        for (i, field) in fields.into_iter().enumerate() {
+            if i != 0 {
+                writer.write_all(b",")?;
+            }
+            write_csv_field(&mut writer, field)?;
+        }
+        writer.write_all(b"\r\n")?;
+    }
+    writer.flush()?;
 
     Ok(())
 }
 
+/// 写出一个 CSV 字段，包含逗号、引号或换行时加上引号，并转义其中的引号。
+fn write_csv_field(writer: &mut impl Write, field: &str) -> io::Result<()> {
+    if field.contains([',', '"', '\r', '\n']) {
+        write!(writer, "\"{}\"", field.replace('"', "\"\""))
+    } else {
+        writer.write_all(field.as_bytes())
+    }
+}
+
 #[derive(Parser)]
 #[command(version, about)]
 struct Cli {
@@ -140,9 +1007,66 @@ struct Cli {
     #[arg(long, default_value = DEFAULT_AUTH_FILE)]
     auth_file: PathBuf,
 
-    /// 指定设备 ID
+    /// 指定设备 ID，可以多次指定以同时操作多个设备
     #[arg(short, long)]
-    device_id: Option<String>,
+    device_id: Vec<String>,
+
+    /// 指定设备名称，忽略大小写，但需要完全匹配
+    #[arg(long, conflicts_with = "device_id")]
+    device_name: Option<String>,
+
+    /// 指定设备在设备列表（即 `device` 命令的输出）中的序号，从 0 开始
+    #[arg(long, value_name = "N", conflicts_with_all = ["device_id", "device_name"])]
+    device_index: Option<usize>,
+
+    /// 同时操作所有设备
+    #[arg(long, conflicts_with_all = ["device_id", "device_name", "device_index"])]
+    all: bool,
+
+    /// 以 JSON 格式输出
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// 不输出操作类命令的结果，只通过退出码表示成败，优先于 --json。
+    /// 查询类命令（如 device、history）仍会输出，但省略标签
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// 演练，只输出将要发送的请求而不真正发送，只读的命令不受影响
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// 播放或播报时按 Ctrl-C 中断，会先停止设备上的播放再退出
+    #[arg(long, global = true)]
+    stop_on_interrupt: bool,
+
+    /// 小米账号所在的地区，目前只有 cn 提供小爱服务
+    #[arg(long, env = "MIAI_REGION", default_value_t = Region::Cn)]
+    region: Region,
+
+    /// 通过 HTTP、HTTPS 或 SOCKS5 代理发送请求，优先于 HTTP_PROXY 和 ALL_PROXY 等环境变量
+    #[arg(long, value_name = "URL")]
+    proxy: Option<Url>,
+
+    /// 接受无效的 TLS 证书，仅用于通过 mitmproxy 等代理调试，会让登录状态暴露在中间人攻击之下
+    #[arg(long)]
+    insecure: bool,
+
+    /// 替换请求小爱服务时的 User-Agent，仅在默认值被服务端拒绝时使用，不匹配的值可能导致认证失败
+    #[arg(long, env = "MIAI_USER_AGENT", value_name = "UA")]
+    user_agent: Option<String>,
+
+    /// 模拟的米家 App 版本，会写入登录和请求的 User-Agent
+    #[arg(long, env = "MIAI_APP_VERSION", value_name = "VERSION")]
+    app_version: Option<String>,
+
+    /// 守护进程的 Unix 套接字，默认为认证文件同目录下的同名 .sock 文件
+    #[arg(long, env = "MIAI_SOCKET", value_name = "PATH")]
+    socket: Option<PathBuf>,
+
+    /// 请求超时的秒数
+    #[arg(long, default_value_t = 10)]
+    timeout_secs: u64,
 
     #[arg(skip)]
     xiaoai: OnceCell<Xiaoai>,
@@ -152,15 +1076,71 @@ struct Cli {
 }
 
 impl Cli {
+    /// 输出操作类命令的结果，指定了 `--quiet` 时不输出。
+    fn print_response(&self, value: &impl Serialize) -> anyhow::Result<()> {
+        if self.quiet {
+            return Ok(());
+        }
+
+        print_json(value)
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+
+    /// 按照全局的选项创建 [`XiaoaiBuilder`]。
+    fn builder(&self) -> XiaoaiBuilder {
+        let mut builder = Xiaoai::builder()
+            .timeout(self.timeout())
+            .region(self.region)
+            .danger_accept_invalid_certs(self.insecure);
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(proxy.clone());
+        }
+        if let Some(user_agent) = &self.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+        if let Some(version) = &self.app_version {
+            builder = builder.app_version(version);
+        }
+        if self.insecure {
+            eprintln!("警告: 已关闭 TLS 证书校验，请仅在调试时使用");
+        }
+
+        builder
+    }
+
     /// 加载 [`Xiaoai`]，仅加载一次然后缓存起来。
     fn xiaoai(&self) -> anyhow::Result<&Xiaoai> {
         self.xiaoai.get_or_try_init(|| {
             let file = File::open(&self.auth_file)
                 .with_context(|| format!("需要可用的认证文件 `{}`", self.auth_file.display()))?;
 
-            Xiaoai::load(BufReader::new(file))
-                .map_err(anyhow::Error::from_boxed)
-                .with_context(|| format!("加载认证文件 `{}` 失败", self.auth_file.display()))
+            let mut builder = self.builder();
+            // 获取本地时区在多线程时可能失败，失败时保持 UTC
+            if let Ok(offset) = UtcOffset::current_local_offset() {
+                builder = builder.utc_offset(offset);
+            }
+            let xiaoai = match builder.load(BufReader::new(file)) {
+                Err(miai::Error::UnsupportedAuthVersion(version)) => bail!(
+                    "认证文件 `{}` 的版本 {version} 不受支持，请使用 `xiaoai login` 重新登录",
+                    self.auth_file.display()
+                ),
+                result => result
+                    .with_context(|| format!("加载认证文件 `{}` 失败", self.auth_file.display()))?,
+            };
+
+            // 登录状态刷新后写回认证文件
+            let auth_file = self.auth_file.clone();
+            Ok(xiaoai.on_refresh(move |xiaoai| {
+                let result = File::create(&auth_file)
+                    .map_err(miai::Error::from)
+                    .and_then(|mut file| xiaoai.save(&mut file));
+                if let Err(err) = result {
+                    eprintln!("保存认证文件 `{}` 失败: {err}", auth_file.display());
+                }
+            }))
         })
     }
 
@@ -179,67 +1159,536 @@ impl Cli {
     /// 获取用户指定的设备 ID。
     ///
     /// 如果用户没有在命令行指定，则会向服务器请求设备列表。
+    /// 如果用户指定了设备名称，会在设备列表中查找名称匹配的设备。
     /// 如果请求结果只有一个设备，会自动选择这个唯一的设备。
     /// 如果请求结果存在多个设备，则会让用户自行选择。
     async fn device_id(&self) -> anyhow::Result<&str> {
-        if let Some(device_id) = &self.device_id {
-            return Ok(device_id);
+        match &self.device_id[..] {
+            [device_id] => return Ok(device_id),
+            [] => ensure!(!self.all, "该命令只能操作单个设备，不支持 --all"),
+            _ => bail!("该命令只能操作单个设备，请只指定一个设备 ID"),
         }
 
         let info = self.device_info().await?;
+        if let Some(name) = &self.device_name {
+            let matched: Vec<_> = info
+                .iter()
+                .filter(|x| x.name.to_lowercase() == name.to_lowercase())
+                .collect();
+            let names = || {
+                info.iter()
+                    .map(|x| format!("`{}`", x.name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            return match matched[..] {
+                [info] => Ok(warn_offline(info)),
+                [] => Err(anyhow!("找不到名为 `{name}` 的设备，可选的有: {}", names())),
+                _ => Err(anyhow!(
+                    "有多个名为 `{name}` 的设备，请使用 --device-id 指定: {}",
+                    matched
+                        .iter()
+                        .map(|x| format!("`{}`({})", x.name, x.device_id))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )),
+            };
+        }
+
         ensure!(!info.is_empty(), "无可用设备，需要在小米音箱 APP 中绑定");
+        if let Some(index) = self.device_index {
+            let info = info.get(index).with_context(|| {
+                format!("设备序号 {index} 超出范围，共有 {} 个设备", info.len())
+            })?;
+            return Ok(warn_offline(info));
+        }
         if info.len() == 1 {
-            return Ok(info[0].device_id.as_str());
+            return Ok(warn_offline(&info[0]));
         }
 
-        let options = info.iter().map(DisplayDeviceInfo).collect();
+        let name_width = DisplayDeviceInfo::name_width(info);
+        let options = info
+            .iter()
+            .map(|info| DisplayDeviceInfo::new(info).compact(name_width))
+            .collect();
         let ans = Select::new("目标设备?", options).prompt()?;
 
-        Ok(&ans.0.device_id)
+        Ok(warn_offline(ans.info))
     }
+    /// 设备明确不支持 `capability` 时提前警告。
+    ///
+    /// 只使用已经获取的设备列表，不会为此额外请求。设备没有提供功能信息时不会警告。
+    fn warn_unsupported(&self, device_ids: &[&str], capability: Capability) {
+        let Some(info) = self.device_info.get() else {
+            return;
+        };
+        let unsupported = info.iter().filter(|info| {
+            device_ids.contains(&info.device_id.as_str())
+                && !info.capabilities.is_empty()
+                && !info.capabilities.contains(capability)
+        });
+        for info in unsupported {
+            eprintln!("警告: 设备 `{}` 可能不支持此操作", info.name);
+        }
+    }
+
+    /// 设备列表的缓存文件，在认证文件旁。
+    fn device_cache_file(&self) -> PathBuf {
+        self.auth_file.with_extension("devices.json")
+    }
+
+    /// 守护进程的 Unix 套接字。
+    #[cfg(unix)]
+    fn socket(&self) -> PathBuf {
+        self.socket
+            .clone()
+            .unwrap_or_else(|| self.auth_file.with_extension("sock"))
+    }
+
+    /// 可以转发给守护进程的请求，参见 [`daemon`]。
+    ///
+    /// 只转发单个设备的 `say`、`volume` 和 `status`，需要选择设备或其他选项时返回 `None`。
+    #[cfg(unix)]
+    fn daemon_request(&self) -> Option<Value> {
+        if self.all
+            || self.device_id.len() > 1
+            || self.device_name.is_some()
+            || self.device_index.is_some()
+            || self.dry_run
+        {
+            return None;
+        }
+        let mut request = match &self.command {
+            Commands::Say {
+                text: Some(text),
+                chunked: false,
+                ..
+            } => json!({ "command": "say", "text": text }),
+            Commands::Volume {
+                volume: None,
+                verify: false,
+                ramp: None,
+            } => json!({ "command": "volume" }),
+            Commands::Volume {
+                volume: Some(VolumeArg::Absolute(volume)),
+                verify: false,
+                ramp: None,
+            } => json!({ "command": "volume", "volume": volume }),
+            Commands::Status => json!({ "command": "status" }),
+            _ => return None,
+        };
+        request["deviceId"] = json!(self.device_id.first());
+        request["json"] = json!(self.json);
+        request["quiet"] = json!(self.quiet);
+
+        Some(request)
+    }
+
+    /// 获取用户指定的多个设备 ID。
+    ///
+    /// 如果指定了 `--all`，则返回所有设备；如果指定了多个设备 ID，则原样返回；
+    /// 否则同 [`Self::device_id`]。
+    async fn device_ids(&self) -> anyhow::Result<Vec<&str>> {
+        if self.all {
+            let info = self.device_info().await?;
+            ensure!(!info.is_empty(), "无可用设备，需要在小米音箱 APP 中绑定");
+            return Ok(info.iter().map(warn_offline).collect());
+        }
+        if self.device_id.len() > 1 {
+            return Ok(self.device_id.iter().map(String::as_str).collect());
+        }
+
+        Ok(vec![self.device_id().await?])
+    }
+}
+
+impl Commands {
+    /// 是否支持 `--dry-run`，只读的命令不受影响，也视为支持。
+    ///
+    /// 需要先读取状态的命令无法演练。
+    fn supports_dry_run(&self) -> bool {
+        match self {
+            Self::Play { sources } => !sources
+                .iter()
+                .any(|source| matches!(source, PlaySource::File(_))),
+            Self::Volume {
+                volume,
+                verify,
+                ramp,
+            } => !verify && ramp.is_none() && !matches!(volume, Some(VolumeArg::Relative(_))),
+            Self::Ask { wait, .. } => !wait,
+            Self::Say { .. }
+            | Self::Pause
+            | Self::Stop
+            | Self::Next
+            | Self::Prev
+            | Self::Ubus { .. } => true,
+            // 只读的命令
+            Self::Device { .. }
+            | Self::Whoami
+            | Self::Status
+            | Self::History { .. }
+            | Self::Watch { .. }
+            | Self::Alarm {
+                command: AlarmCommands::List,
+            }
+            | Self::Dnd {
+                command: DndCommands::Status,
+            }
+            | Self::Mode { mode: None } => true,
+            _ => false,
+        }
+    }
+
+    /// 是否会持续一段时间地播放或播报，参见 `--stop-on-interrupt`。
+    fn is_playback(&self) -> bool {
+        matches!(self, Self::Say { .. } | Self::Play { .. })
+    }
+
+    /// 命令需要设备支持的功能。
+    fn capability(&self) -> Option<Capability> {
+        match self {
+            Self::Say { .. } => Some(Capability::Tts),
+            Self::Play { .. }
+            | Self::Pause
+            | Self::Stop
+            | Self::Next
+            | Self::Prev
+            | Self::Mode { .. }
+            | Self::Music { .. } => Some(Capability::Playback),
+            _ => None,
+        }
+    }
+}
+
+/// 设备离线时提前警告，返回设备 ID。
+fn warn_offline(info: &DeviceInfo) -> &str {
+    if !info.online {
+        eprintln!("警告: 设备 `{}` 当前离线，命令可能会失败", info.name);
+    }
+
+    &info.device_id
 }
 
 #[derive(Debug, Subcommand)]
 enum Commands {
     /// 登录以获得认证
-    Login,
+    Login {
+        /// 使用米家 App 扫码登录，可以避免密码登录触发的风控验证
+        #[arg(long)]
+        qr: bool,
+        /// 账号，不指定时会提示输入，扫码登录时忽略
+        #[arg(long, env = "MIAI_USERNAME")]
+        username: Option<String>,
+        /// 密码，不指定时会提示输入。注意命令行参数可能被其他用户看到，最好使用环境变量
+        #[arg(long, env = "MIAI_PASSWORD", hide_env_values = true)]
+        password: Option<String>,
+    },
+    /// 注销登录并删除认证文件
+    Logout,
+    /// 输出认证文件所属账号的小米 ID
+    Whoami,
     /// 列出设备
-    Device,
+    ///
+    /// 获取成功后会缓存到认证文件旁，之后可以通过 --cached 在不发出请求的情况下查看。
+    Device {
+        /// 读取上次缓存的设备列表，不发出请求
+        #[arg(long)]
+        cached: bool,
+    },
+    /// 重命名设备
+    Rename { name: String },
     /// 播报文本
-    Say { text: String },
+    Say {
+        /// 要播报的文本，为 `-` 时从标准输入读取
+        #[arg(required_unless_present = "file", conflicts_with = "file")]
+        text: Option<String>,
+        /// 从 UTF-8 编码的文件读取要播报的文本
+        #[arg(long, value_name = "PATH")]
+        file: Option<PathBuf>,
+        /// 分段播报长文本，每段播报完毕后再发送下一段，避免被截断
+        #[arg(long)]
+        chunked: bool,
+    },
     /// 播放
     Play {
-        /// 可选的音乐链接
-        url: Option<Url>,
+        /// 可选的音乐链接或本地文件路径，本地文件需要小爱能访问本机
+        ///
+        /// 指定多个链接时会依次播放，直到开始播放最后一首才退出，无效的链接会被跳过
+        sources: Vec<PlaySource>,
+    },
+    /// 搜索并播放音乐，依赖账号绑定的音乐会员
+    Music {
+        /// 搜索的内容，如歌手和歌名
+        #[arg(required = true)]
+        query: Vec<String>,
     },
     /// 暂停
     Pause,
-    /// 停止
+    /// 停止，包括正在进行的播报
     Stop,
+    /// 下一首
+    Next,
+    /// 上一首
+    Prev,
     /// 调整音量
-    Volume { volume: u32 },
+    Volume {
+        /// 目标音量，带 +/- 号时为相对调整，不指定则查询当前音量
+        #[arg(allow_negative_numbers = true)]
+        volume: Option<VolumeArg>,
+        /// 设置后读回音量，确认已经生效，只对绝对的音量有效
+        #[arg(long)]
+        verify: bool,
+        /// 在这段时间内逐步调整到目标音量，如 30s、1m，只对绝对的音量有效
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration, requires = "volume", conflicts_with = "verify")]
+        ramp: Option<Duration>,
+    },
+    /// 静音，输出静音前的音量
+    Mute,
+    /// 取消静音，输出恢复后的音量
+    Unmute,
     /// 询问
-    Ask { text: String },
+    Ask {
+        text: String,
+        /// 等待并输出小爱的回答
+        #[arg(long)]
+        wait: bool,
+        /// 静默执行，小爱不会播报回答，对话记录中通常仍可以看到
+        #[arg(long, conflicts_with = "wait")]
+        silent: bool,
+    },
+    /// 播放器状态
+    Status,
+    /// 设置或查询循环播放模式
+    Mode {
+        /// sequence、repeat-all、repeat-one 或 shuffle，不指定则查询当前模式
+        mode: Option<PlayMode>,
+    },
+    /// 定时停止播放
+    Sleep {
+        /// 时长，如 30m、1h、90s
+        #[arg(value_parser = parse_duration, required_unless_present = "cancel")]
+        duration: Option<Duration>,
+        /// 取消定时
+        #[arg(long, conflicts_with = "duration")]
+        cancel: bool,
+    },
+    /// 闹钟
+    Alarm {
+        #[command(subcommand)]
+        command: AlarmCommands,
+    },
+    /// 勿扰模式
+    Dnd {
+        #[command(subcommand)]
+        command: DndCommands,
+    },
     /// 对话记录
     History {
-        /// 最大条数
-        #[arg(short = 'n', long, default_value_t = 1)]
-        limit: u32,
+        /// 最大条数，默认为 1，指定了 --since 时默认不限
+        #[arg(short = 'n', long)]
+        limit: Option<u32>,
+        /// 只显示该时间及之后的记录，可以是 RFC 3339 格式的时间，或距今的时长如 2d、3h
+        #[arg(long, value_parser = parse_time)]
+        since: Option<OffsetDateTime>,
+        /// 只显示该时间之前的记录，不包括该时间，格式同 --since
+        #[arg(long, value_parser = parse_time)]
+        until: Option<OffsetDateTime>,
+        /// 只显示带有该类型应答的记录，如 tts、llm，可以多次指定
+        #[arg(long = "type", value_name = "TYPE")]
+        kinds: Vec<String>,
+        /// 以 CSV 格式导出到文件
+        #[arg(long, value_name = "FILE")]
+        csv: Option<PathBuf>,
+        /// 从旧到新输出，默认从新到旧
+        #[arg(long, visible_alias = "oldest-first")]
+        reverse: bool,
+        /// 同时有多个应答时，--csv 和 --quiet 优先选取的应答类型，以逗号分隔
+        #[arg(
+            long,
+            value_name = "TYPES",
+            value_delimiter = ',',
+            default_value = "llm,tts"
+        )]
+        prefer: Vec<String>,
+        /// 边获取边输出，每行一条 JSON 格式的记录，字段与 --json 相同
+        #[arg(long, conflicts_with_all = ["csv", "reverse"])]
+        ndjson: bool,
+        /// 以 UTC 输出时间，默认使用本地时区
+        #[arg(long, conflicts_with = "tz")]
+        utc: bool,
+        /// 以固定的时区偏移输出时间，如 +08:00、-05:00
+        #[arg(long, value_name = "OFFSET", value_parser = parse_offset, allow_hyphen_values = true)]
+        tz: Option<UtcOffset>,
     },
+    /// 持续输出新的对话记录，按 Ctrl-C 退出
+    Watch {
+        /// 查询的间隔，如 3s、1m
+        #[arg(short, long, value_parser = parse_duration, default_value = "3s")]
+        interval: Duration,
+    },
+    /// 检查登录状态和设备是否可用，没有副作用，可用于健康检查
+    ///
+    /// 成功时退出码为 0，设备离线时为 69，其他失败为 1
+    Ping,
+    /// 进入交互模式，连续执行多条命令而无需重复加载
+    Repl,
+    /// 启动守护进程，保持登录状态和连接，之后的 say、volume 和 status 命令会转发给它执行
+    ///
+    /// 没有守护进程在运行时，命令仍会直接执行。可以用 --device-id 等指定默认设备
+    Daemon,
+    /// 生成 Shell 补全脚本
+    Completions { shell: Shell },
     /// OpenWrt UBUS call
+    ///
+    /// 例如查询播放器状态: xiaoai ubus mediaplayer player_get_play_status '{"media":"app_ios"}' --info
     Ubus {
         path: String,
         method: String,
+        /// 方法的参数，需要是 JSON
+        #[arg(value_parser = parse_json)]
         message: String,
+        /// 校验设备上的调用结果，只输出解析后的 info
+        #[arg(long)]
+        info: bool,
     },
 }
 
-struct DisplayDeviceInfo<'a>(&'a DeviceInfo);
+/// 绝对或相对的音量。
+#[derive(Clone, Copy, Debug)]
+enum VolumeArg {
+    Absolute(u32),
+    Relative(i32),
+}
+
+impl FromStr for VolumeArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |err: ParseIntError| format!("无效的音量 `{s}`: {err}");
+        if s.starts_with(['+', '-']) {
+            return Ok(Self::Relative(s.parse().map_err(invalid)?));
+        }
+        // 在请求前就拒绝超出范围的音量
+        let volume = s.parse().map_err(invalid)?;
+        if volume > MAX_VOLUME {
+            return Err(format!("音量需要在 0 到 {MAX_VOLUME} 之间"));
+        }
+
+        Ok(Self::Absolute(volume))
+    }
+}
+
+/// 要播放的音乐，`http` 或 `https` 链接以外的都视为本地文件。
+#[derive(Clone, Debug)]
+enum PlaySource {
+    Url(Url),
+    File(PathBuf),
+}
 
-impl Display for DisplayDeviceInfo<'_> {
+impl Display for PlaySource {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "名称: {}", self.0.name)?;
-        writeln!(f, "ID:   {}", self.0.device_id)?;
-        writeln!(f, "机型: {}", self.0.hardware)
+        match self {
+            Self::Url(url) => write!(f, "{url}"),
+            Self::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+impl FromStr for PlaySource {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match Url::parse(s) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => Ok(Self::Url(url)),
+            _ => Ok(Self::File(s.into())),
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum AlarmCommands {
+    /// 列出闹钟
+    List,
+    /// 添加闹钟
+    Add {
+        /// 响铃时间，格式为 HH:MM
+        #[arg(value_parser = alarm::parse_time)]
+        time: Time,
+        /// 重复规则，可以是 once、daily、weekdays、weekends 或逗号分隔的星期，如 1,3,5
+        #[arg(short, long, default_value_t = Repeat::Once)]
+        repeat: Repeat,
+        /// 标签
+        #[arg(short, long)]
+        label: Option<String>,
+    },
+    /// 删除闹钟
+    Delete {
+        /// 闹钟 ID
+        id: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum DndCommands {
+    /// 开启勿扰模式，不指定时间时为全天勿扰
+    On {
+        /// 开始时间，格式为 HH:MM
+        #[arg(long, value_parser = alarm::parse_time, requires = "end")]
+        start: Option<Time>,
+        /// 结束时间，格式为 HH:MM
+        #[arg(long, value_parser = alarm::parse_time, requires = "start")]
+        end: Option<Time>,
+    },
+    /// 关闭勿扰模式
+    Off,
+    /// 查询勿扰模式
+    Status,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answer(value: Value) -> Answer {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn completions_for_every_shell() {
+        use clap::ValueEnum;
+
+        for &shell in Shell::value_variants() {
+            let mut output = Vec::new();
+            clap_complete::generate(shell, &mut Cli::command(), "xiaoai", &mut output);
+            assert!(!output.is_empty(), "{shell:?} 的补全脚本为空");
+        }
+    }
+
+    #[test]
+    fn render_answer_golden() {
+        let cases = [
+            (json!({"type": "TTS", "tts": {"text": "你好"}}), "你好"),
+            (
+                json!({"type": "LLM", "llm": {"text": "我是小爱"}}),
+                "我是小爱",
+            ),
+            (
+                json!({"type": "MUSIC", "music": {"title": "晴天", "artist": "周杰伦"}}),
+                "▶ 晴天 - 周杰伦",
+            ),
+            (
+                json!({"type": "ALARM", "alarm": {"time": "07:00"}, "status": "ok"}),
+                r#"alarm: {"time":"07:00"}, status: ok"#,
+            ),
+            (
+                json!({"type": "UNKNOWN", "reminder": {"text": "喝水"}}),
+                "喝水",
+            ),
+            (json!({"type": "TTS", "tts": {"text": "  "}}), "（无文本）"),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(render_answer(&answer(value)), expected);
+        }
     }
 }
@@ -0,0 +1,39 @@
+//! 逐一设置循环播放模式并读回。
+//!
+//! 可以用来检查设备支持哪些 [`PlayMode`]，不支持的模式会返回 [`Error::Unsupported`]。
+
+use std::env;
+
+use miai::{Error, PlayMode, Xiaoai};
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .init();
+
+    let _ = dotenvy::dotenv();
+
+    let username = env::var("MI_USER").expect("env::var");
+    let password = env::var("MI_PASS").expect("env::var");
+    let xiaoai = Xiaoai::login(&username, &password).await.expect("login");
+
+    let device_info = xiaoai.device_info().await.expect("device_info");
+    for info in device_info {
+        println!("设备 {}:", info.name);
+        let original = xiaoai.play_mode(&info.device_id).await.expect("play_mode");
+
+        for mode in PlayMode::ALL {
+            match xiaoai.set_play_mode(&info.device_id, mode).await {
+                Ok(_) => println!("  {mode}: 支持"),
+                Err(Error::Unsupported(_)) => println!("  {mode}: 不支持"),
+                Err(err) => panic!("set_play_mode: {err}"),
+            }
+        }
+
+        xiaoai
+            .set_play_mode(&info.device_id, original)
+            .await
+            .expect("set_play_mode");
+    }
+}
@@ -0,0 +1,152 @@
+//! 小爱闹钟相关类型。
+
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use time::Time;
+
+/// 小爱设备上的闹钟。
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Alarm {
+    /// 闹钟的 ID，由设备分配。
+    ///
+    /// 创建闹钟时无需指定。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+
+    /// 响铃的时间，精确到分钟。
+    #[serde(with = "hh_mm")]
+    pub time: Time,
+
+    /// 重复规则。
+    #[serde(default)]
+    pub repeat: Repeat,
+
+    /// 闹钟的标签。
+    #[serde(default)]
+    pub label: String,
+
+    /// 是否启用。
+    #[serde(default = "enabled", alias = "enable")]
+    pub enabled: bool,
+}
+
+impl Alarm {
+    /// 创建一个启用的闹钟。
+    pub fn new(time: Time, repeat: Repeat) -> Self {
+        Self {
+            id: None,
+            time,
+            repeat,
+            label: String::new(),
+            enabled: true,
+        }
+    }
+}
+
+/// 闹钟的重复规则。
+///
+/// 可以和字符串相互转换，分别为 `once`、`daily`、`weekdays`、`weekends`，
+/// 以及逗号分隔的星期（1 到 7 分别表示周一到周日），如 `1,3,5`。
+///
+/// # Examples
+///
+/// ```
+/// # use miai::alarm::Repeat;
+/// let repeat: Repeat = "1,3,5".parse().unwrap();
+/// assert_eq!(repeat, Repeat::Days(vec![1, 3, 5]));
+/// assert_eq!(repeat.to_string(), "1,3,5");
+/// assert_eq!("weekdays".parse::<Repeat>().unwrap(), Repeat::Weekdays);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Repeat {
+    /// 只响一次。
+    #[default]
+    Once,
+    /// 每天。
+    Daily,
+    /// 工作日，即周一到周五。
+    Weekdays,
+    /// 周末。
+    Weekends,
+    /// 指定的星期，1 到 7 分别表示周一到周日。
+    Days(Vec<u8>),
+}
+
+impl fmt::Display for Repeat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Once => f.write_str("once"),
+            Self::Daily => f.write_str("daily"),
+            Self::Weekdays => f.write_str("weekdays"),
+            Self::Weekends => f.write_str("weekends"),
+            Self::Days(days) => {
+                let days: Vec<_> = days.iter().map(u8::to_string).collect();
+                f.write_str(&days.join(","))
+            }
+        }
+    }
+}
+
+impl FromStr for Repeat {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let repeat = match s.trim() {
+            "once" => Self::Once,
+            "daily" => Self::Daily,
+            "weekdays" => Self::Weekdays,
+            "weekends" => Self::Weekends,
+            days => {
+                let days = days
+                    .split(',')
+                    .map(|day| day.trim().parse().ok().filter(|day| (1..=7).contains(day)))
+                    .collect::<Option<Vec<u8>>>()
+                    .ok_or_else(|| crate::Error::InvalidInput(format!("无效的重复规则 `{s}`")))?;
+                Self::Days(days)
+            }
+        };
+
+        Ok(repeat)
+    }
+}
+
+/// 解析 `HH:MM` 格式的时间。
+///
+/// # Examples
+///
+/// ```
+/// # use miai::alarm::parse_time;
+/// let time = parse_time("07:30").unwrap();
+/// assert_eq!((time.hour(), time.minute()), (7, 30));
+/// assert!(parse_time("25:00").is_err());
+/// ```
+pub fn parse_time(s: &str) -> crate::Result<Time> {
+    let invalid = || crate::Error::InvalidInput(format!("无效的时间 `{s}`，应为 HH:MM"));
+    let (hour, minute) = s.trim().split_once(':').ok_or_else(invalid)?;
+    let hour = hour.parse().map_err(|_| invalid())?;
+    let minute = minute.parse().map_err(|_| invalid())?;
+
+    Time::from_hms(hour, minute, 0).map_err(|_| invalid())
+}
+
+fn enabled() -> bool {
+    true
+}
+
+/// 以 `HH:MM` 格式序列化时间。
+pub(crate) mod hh_mm {
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+    use time::Time;
+
+    pub fn serialize<S: Serializer>(time: &Time, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{:02}:{:02}", time.hour(), time.minute()))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Time, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        super::parse_time(&s).map_err(D::Error::custom)
+    }
+}
@@ -0,0 +1,123 @@
+use std::{
+    convert::Infallible,
+    fmt,
+    io::{self, BufRead, Write},
+};
+
+use cookie_store::{Cookie, CookieStore};
+use serde::{Deserialize, Deserializer, Serialize, de::Error as _};
+use serde_json::Value;
+
+/// 当前保存的认证文件版本。
+///
+/// 版本 1 是没有版本字段的 Cookies 数组，版本 2 起使用 `{ "version": N, "cookies": [...] }` 的格式。
+pub(crate) const AUTH_VERSION: u32 = 2;
+
+/// 可序列化的登录状态，包括登录服务的 `passToken` 在内的所有 Cookies。
+///
+/// 通过 [`Xiaoai::auth_state`][crate::Xiaoai::auth_state] 获得，可以用任意格式保存到任意位置，
+/// 比如应用的密钥存储，再通过 [`Xiaoai::from_auth_state`][crate::Xiaoai::from_auth_state] 恢复。
+/// 序列化后带有格式的版本号，反序列化时兼容旧版本，未知的版本会失败。
+///
+/// 其中的内容等同于密码，请注意安全性。
+///
+/// # Examples
+///
+/// ```
+/// use miai::{AuthState, Xiaoai, json};
+///
+/// let xiaoai = Xiaoai::load(b"[]".as_slice())?;
+/// let saved = json::to_string(&xiaoai.auth_state())?;
+///
+/// let state: AuthState = json::from_str(&saved)?;
+/// let xiaoai = Xiaoai::from_auth_state(state)?;
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Serialize)]
+pub struct AuthState {
+    version: u32,
+    cookies: Vec<Cookie<'static>>,
+}
+
+impl AuthState {
+    /// 保存所有 Cookies，包括已过期和非持久的。
+    ///
+    /// Cookies 按域名、路径和名称排序，同样的登录状态总是保存为同样的内容。
+    pub(crate) fn from_cookie_store(cookie_store: &CookieStore) -> Self {
+        let mut cookies: Vec<_> = cookie_store.iter_any().cloned().collect();
+        cookies.sort_by(|a, b| (&a.domain, &a.path, a.name()).cmp(&(&b.domain, &b.path, b.name())));
+
+        Self {
+            version: AUTH_VERSION,
+            cookies,
+        }
+    }
+
+    pub(crate) fn into_cookie_store(self) -> CookieStore {
+        CookieStore::from_cookies(self.cookies.into_iter().map(Ok::<_, Infallible>), true)
+            .unwrap_or_else(|never| match never {})
+    }
+
+    /// 从 JSON 解析，兼容旧版本。
+    ///
+    /// 未知的版本返回 [`Error::UnsupportedAuthVersion`][crate::Error::UnsupportedAuthVersion]。
+    fn from_value(value: Value) -> crate::Result<Self> {
+        let version = match &value {
+            Value::Array(_) => 1,
+            value => value
+                .get("version")
+                .and_then(Value::as_u64)
+                .map_or(0, |version| u32::try_from(version).unwrap_or(u32::MAX)),
+        };
+        let cookies = match version {
+            // 迁移版本 1，直接读取数组
+            1 => serde_json::from_value(value)?,
+            AUTH_VERSION => {
+                #[derive(Deserialize)]
+                struct Current {
+                    cookies: Vec<Cookie<'static>>,
+                }
+                serde_json::from_value::<Current>(value)?.cookies
+            }
+            version => return Err(crate::Error::UnsupportedAuthVersion(version)),
+        };
+
+        Ok(Self {
+            version: AUTH_VERSION,
+            cookies,
+        })
+    }
+}
+
+/// 不输出 Cookies 的内容，避免泄露到日志中。
+impl fmt::Debug for AuthState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthState")
+            .field("version", &self.version)
+            .field("cookies", &self.cookies.len())
+            .finish()
+    }
+}
+
+impl<'de> Deserialize<'de> for AuthState {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Self::from_value(Value::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
+
+/// 以 JSON 格式保存 [`AuthState`]。
+///
+/// 先序列化到内存再写入，序列化失败时不会写入任何内容，错误转换为 [`io::ErrorKind::InvalidData`]。
+pub(crate) fn to_writer(auth_state: &AuthState, mut writer: impl Write) -> io::Result<()> {
+    let mut json = serde_json::to_vec_pretty(auth_state)?;
+    json.push(b'\n');
+
+    writer.write_all(&json)
+}
+
+/// 加载 [`to_writer`] 保存的 [`AuthState`]。
+///
+/// 不经过 [`Deserialize`]，以保留 [`Error::UnsupportedAuthVersion`][crate::Error::UnsupportedAuthVersion]。
+pub(crate) fn load<R: BufRead>(reader: R) -> crate::Result<AuthState> {
+    AuthState::from_value(serde_json::from_reader(reader)?)
+}
@@ -0,0 +1,398 @@
+use std::{io::BufRead, sync::Arc, time::Duration};
+
+use reqwest::{Client, Url, header::HeaderValue};
+use reqwest_cookie_store::CookieStoreMutex;
+use time::UtcOffset;
+
+#[cfg(feature = "test-util")]
+use crate::xiaoai::MockRefresh;
+use crate::{
+    AuthState, Region, Transport, Xiaoai, auth_file,
+    device_cache::DeviceCache,
+    login::{Login, QrCode, Verification},
+    rate_limit::RateLimiter,
+    retry::RetryPolicy,
+    util::HttpOptions,
+};
+
+/// 默认模拟的米家 App 版本，参见 [`XiaoaiBuilder::app_version`]。
+pub const DEFAULT_APP_VERSION: &str = "6.0.103";
+
+/// 以米家 App 版本 `version` 请求小爱服务时的 User-Agent。
+pub(crate) fn api_user_agent(version: &str) -> String {
+    format!(
+        "MiHome/{version} (com.xiaomi.mihome; build:{version}.1; iOS 14.4.0) Alamofire/{version} MICO/iOSApp/appStore/{version}"
+    )
+}
+
+/// 构造 [`Xiaoai`]，可以对其进行更细致的配置。
+///
+/// # Examples
+///
+/// ```no_run
+/// # use miai::Xiaoai;
+/// # async fn login(client: reqwest::Client) -> miai::Result<()> {
+/// // 使用自己的 `reqwest::Client`
+/// let xiaoai = Xiaoai::builder()
+///     .client(client)
+///     .login("username", "password")
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct XiaoaiBuilder {
+    client: Option<Client>,
+    timeout: Option<Duration>,
+    retry: RetryPolicy,
+    rate_limit: Option<(Duration, usize)>,
+    utc_offset: Option<UtcOffset>,
+    device_cache_ttl: Option<Duration>,
+    region: Region,
+    http: HttpOptions,
+    transport: Option<Arc<dyn Transport>>,
+    #[cfg(feature = "test-util")]
+    mock_refresh: Option<MockRefresh>,
+}
+
+impl XiaoaiBuilder {
+    /// 创建默认配置的 `XiaoaiBuilder`，同 [`Xiaoai::builder`]。
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 指定用于请求小爱服务的 [`reqwest::Client`]。
+    ///
+    /// 登录状态不会写入这个 `Client`，`Xiaoai` 会在每次请求时自行附带 Cookies 和 User-Agent，
+    /// 因此同一个 `Client` 可以安全地在多个账号、乃至整个应用间共享。
+    /// 登录本身仍使用内部的 `Client` 进行。
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// 为每个请求设置超时，包括登录和刷新登录状态的请求。
+    ///
+    /// 超时后请求会返回 [`Error::Timeout`][crate::Error::Timeout]。默认不设超时。
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// 启用重试，请求遇到暂时性的错误时会以指数退避的方式重试。
+    ///
+    /// 总共最多尝试 `max_attempts` 次，第一次重试前等待约 `base_delay`，之后每次翻倍，并带有随机抖动。
+    /// 只有网络错误、超时、服务端 5xx 错误和无法解析的响应体会重试，4xx 和登录失效则不会。
+    ///
+    /// 注意 [`Xiaoai::tts`] 等操作类请求同样会被重试。由于错误通常发生在请求到达设备之前，
+    /// 这些请求在实践中可以视为幂等的，但极端情况下设备仍可能执行两次。默认不重试。
+    pub fn retry(mut self, max_attempts: u32, base_delay: Duration) -> Self {
+        self.retry = RetryPolicy {
+            max_attempts: max_attempts.max(1),
+            base_delay,
+        };
+        self
+    }
+
+    /// 启用客户端限速，每 `per` 最多发出 `burst` 个请求，超出的请求会排队等待。
+    ///
+    /// 可以避免短时间内的大量请求触发风控。限速是客户端自愿遵守的，对所有克隆的 [`Xiaoai`] 共享，
+    /// 但不同 `XiaoaiBuilder` 构造的 `Xiaoai` 之间互不影响。重试的请求同样受限，登录的请求则不受限。
+    /// 默认不限速。
+    pub fn rate_limit(mut self, per: Duration, burst: usize) -> Self {
+        self.rate_limit = Some((per, burst));
+        self
+    }
+
+    /// 设置返回时间时使用的时区偏移，比如对话记录的时间。默认为 UTC。
+    ///
+    /// 只改变时间的偏移，表示的时刻不变，随时可以用 [`OffsetDateTime::to_offset`][time::OffsetDateTime::to_offset]
+    /// 换算回 UTC。本地时区可以通过 [`UtcOffset::current_local_offset`] 获得，但在多线程的程序中它可能会失败，
+    /// 因此最好在启动其他线程前获取。
+    pub fn utc_offset(mut self, offset: UtcOffset) -> Self {
+        self.utc_offset = Some(offset);
+        self
+    }
+
+    /// 缓存设备列表，[`Xiaoai::device_info`] 在 `ttl` 内返回缓存的结果，过期后重新获取。
+    ///
+    /// 适合长期运行的服务，既能避免频繁请求，又能发现设备的增减。并发的调用者共享同一个请求，
+    /// 缓存对所有克隆的 [`Xiaoai`] 共享，可以通过 [`Xiaoai::refresh_devices`] 强制刷新。默认不缓存。
+    pub fn device_cache(mut self, ttl: Duration) -> Self {
+        self.device_cache_ttl = Some(ttl);
+        self
+    }
+
+    /// 替换发送小爱服务请求的 HTTP 层，参见 [`Transport`]。
+    ///
+    /// 主要用于测试，比如启用 `test-util` 特性后使用 `MockTransport`。替换后 [`Self::client`] 只用于构造请求，
+    /// `Xiaoai` 会在每次请求时自行附带 Cookies 和 User-Agent。登录仍使用内部的 `Client` 进行。
+    pub fn transport(mut self, transport: impl Transport) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// 以 `refresh` 代替登录服务刷新登录状态，需要启用 `test-util` 特性。
+    ///
+    /// 刷新登录状态的请求发往登录服务，不经过 [`Transport`]。配合 `MockTransport` 模拟登录失效时，
+    /// 可以用它模拟刷新的结果，并统计刷新的次数。成功后同样会调用 [`Xiaoai::on_refresh`] 注册的回调。
+    ///
+    /// # Examples
+    ///
+    /// 并发的请求同时遇到登录失效时，只会刷新一次：
+    ///
+    /// ```
+    /// # use std::{
+    /// #     sync::{
+    /// #         Arc,
+    /// #         atomic::{AtomicUsize, Ordering},
+    /// #     },
+    /// #     time::Duration,
+    /// # };
+    /// # use miai::{MockTransport, Xiaoai};
+    /// # use reqwest::{Method, StatusCode};
+    /// # use serde_json::json;
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> miai::Result<()> {
+    /// let mock = MockTransport::new();
+    /// // 三个请求都在刷新前发出，都会收到登录失效的响应
+    /// mock.delay(Duration::from_millis(50));
+    /// for _ in 0..3 {
+    ///     mock.raw_respond(Method::POST, "remote/ubus", StatusCode::UNAUTHORIZED, "");
+    /// }
+    /// mock.respond(Method::POST, "remote/ubus", json!({}));
+    ///
+    /// let refreshes = Arc::new(AtomicUsize::new(0));
+    /// let counter = Arc::clone(&refreshes);
+    /// let xiaoai = Xiaoai::builder()
+    ///     .transport(mock.clone())
+    ///     .mock_refresh(move || {
+    ///         counter.fetch_add(1, Ordering::SeqCst);
+    ///         Ok(())
+    ///     })
+    ///     .load(b"[]".as_slice())?;
+    ///
+    /// let (a, b, c) = tokio::join!(
+    ///     xiaoai.tts("device_id", "一"),
+    ///     xiaoai.tts("device_id", "二"),
+    ///     xiaoai.tts("device_id", "三"),
+    /// );
+    /// assert!(a.is_ok() && b.is_ok() && c.is_ok());
+    /// assert_eq!(refreshes.load(Ordering::SeqCst), 1);
+    /// // 每个请求在刷新后各重试一次
+    /// assert_eq!(mock.requests().len(), 6);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "test-util")]
+    pub fn mock_refresh<F>(mut self, refresh: F) -> Self
+    where
+        F: Fn() -> crate::Result<()> + Send + Sync + 'static,
+    {
+        self.mock_refresh = Some(MockRefresh(Arc::new(refresh)));
+        self
+    }
+
+    /// 设置小米账号所在的地区，默认为 [`Region::Cn`]。
+    ///
+    /// 目前只有中国大陆提供小爱服务，选择其他地区时，登录和加载都会在发出请求前返回
+    /// [`Error::UnsupportedRegion`][crate::Error::UnsupportedRegion]。
+    pub fn region(mut self, region: Region) -> Self {
+        self.region = region;
+        self
+    }
+
+    /// 通过代理发送所有请求，包括登录的请求，支持 HTTP、HTTPS 和 SOCKS5 代理。
+    ///
+    /// SOCKS5 代理使用 `socks5://` 或 `socks5h://`，后者由代理解析域名。默认会读取 `HTTP_PROXY`、
+    /// `HTTPS_PROXY` 和 `ALL_PROXY` 等环境变量，指定后环境变量不再生效。
+    /// 代理的地址无效或是其他协议时，登录和加载会返回 [`Error::InvalidInput`][crate::Error::InvalidInput]。
+    /// 通过 [`XiaoaiBuilder::client`] 传入的 `Client` 不受影响。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use miai::{Error, Xiaoai};
+    /// let proxy = "socks5h://127.0.0.1:1080".parse().unwrap();
+    /// assert!(Xiaoai::builder().proxy(proxy).load(b"[]".as_slice()).is_ok());
+    ///
+    /// let proxy = "ftp://127.0.0.1:21".parse().unwrap();
+    /// let result = Xiaoai::builder().proxy(proxy).load(b"[]".as_slice());
+    /// assert!(matches!(result, Err(Error::InvalidInput(_))));
+    /// ```
+    pub fn proxy(mut self, proxy: Url) -> Self {
+        self.http.proxy = Some(proxy);
+        self
+    }
+
+    /// 接受无效的 TLS 证书，仅用于通过 mitmproxy 等代理调试。
+    ///
+    /// **危险**：这会让请求暴露在中间人攻击之下，包括账号密码和登录状态。默认不接受。
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.http.accept_invalid_certs = accept;
+        self
+    }
+
+    /// 替换请求小爱服务时的 User-Agent，默认模拟米家 App，参见 [`Self::app_version`]。
+    ///
+    /// 服务端会根据 User-Agent 判断客户端，与 App 版本不符或无法识别的值可能导致所有请求都认证失败，
+    /// 只建议在默认值被服务端拒绝、又来不及更新本库时使用。登录的请求不受影响。
+    /// 包含换行等无效字符时，登录和加载会返回 [`Error::InvalidInput`][crate::Error::InvalidInput]。
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.http.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// 设置模拟的米家 App 版本，如 `6.0.103`，默认为 [`DEFAULT_APP_VERSION`]。
+    ///
+    /// 版本会写入登录和小爱服务请求的 User-Agent，服务端不再接受旧版本时可以借此更新。
+    /// 请使用真实存在的版本号，否则同样可能被拒绝。指定了 [`Self::user_agent`] 时只影响登录的请求。
+    pub fn app_version(mut self, version: impl Into<String>) -> Self {
+        self.http.app_version = Some(version.into());
+        self
+    }
+
+    /// 登录以调用小爱服务。
+    ///
+    /// 需要短信或邮箱验证时会返回 [`Error::VerificationRequired`][crate::Error::VerificationRequired]，
+    /// 参见 [`XiaoaiBuilder::login_with_verification`]。
+    pub async fn login(self, username: &str, password: &str) -> crate::Result<Xiaoai> {
+        self.api_server()?;
+        let mut login = Login::new(username, password)?.with_http_options(&self.http)?;
+        if let Some(timeout) = self.timeout {
+            login = login.with_timeout(timeout);
+        }
+        let login_response = login.login().await?;
+        let auth_response = login.auth(login_response).await?;
+        login.get_token(auth_response).await?;
+
+        self.from_login(login)
+    }
+
+    /// 登录以调用小爱服务，并在需要时完成短信或邮箱验证。
+    ///
+    /// 如果服务端要求验证，会先发送验证码，再调用 `on_verification` 获取用户收到的验证码。
+    /// `on_verification` 返回 `None` 时放弃登录，返回 [`Error::VerificationRequired`][crate::Error::VerificationRequired]。
+    pub async fn login_with_verification(
+        self,
+        username: &str,
+        password: &str,
+        on_verification: impl FnOnce(&Verification) -> Option<String>,
+    ) -> crate::Result<Xiaoai> {
+        self.api_server()?;
+        let mut login = Login::new(username, password)?.with_http_options(&self.http)?;
+        if let Some(timeout) = self.timeout {
+            login = login.with_timeout(timeout);
+        }
+        let login_response = login.login().await?;
+        let auth_response = match login.auth(login_response).await {
+            Err(crate::Error::VerificationRequired(challenge)) => {
+                let verification = login.start_verification(&challenge).await?;
+                let Some(code) = on_verification(&verification) else {
+                    return Err(crate::Error::VerificationRequired(challenge));
+                };
+                login.submit_code(&verification, &code).await?
+            }
+            result => result?,
+        };
+        login.get_token(auth_response).await?;
+
+        self.from_login(login)
+    }
+
+    /// 扫码登录以调用小爱服务。
+    ///
+    /// 获取到二维码后会调用 `on_qr_code`，应将 [`QrCode::login_url`] 展示给用户，
+    /// 用米家 App 扫描并确认后登录完成。参见 [`Login::wait_qr_code`]。
+    pub async fn login_qr(self, on_qr_code: impl FnOnce(&QrCode)) -> crate::Result<Xiaoai> {
+        self.api_server()?;
+        let mut login = Login::new_qr()?.with_http_options(&self.http)?;
+        if let Some(timeout) = self.timeout {
+            login = login.with_timeout(timeout);
+        }
+        let login_response = login.login().await?;
+        let qr_code = login.qr_code(login_response).await?;
+        on_qr_code(&qr_code);
+        let auth_response = login.wait_qr_code(&qr_code).await?;
+        login.get_token(auth_response).await?;
+
+        self.from_login(login)
+    }
+
+    /// 从 [`Login`][`crate::login::Login`] 构造。
+    pub fn from_login(self, login: Login) -> crate::Result<Xiaoai> {
+        self.build(login.into_cookie_store())
+    }
+
+    /// 从 `reader` 加载登录状态。
+    ///
+    /// 参见 [`Xiaoai::load`]。
+    pub fn load<R: BufRead>(self, reader: R) -> crate::Result<Xiaoai> {
+        self.from_auth_state(auth_file::load(reader)?)
+    }
+
+    /// 从 [`AuthState`] 恢复登录状态。
+    ///
+    /// 参见 [`Xiaoai::from_auth_state`]。
+    pub fn from_auth_state(self, auth_state: AuthState) -> crate::Result<Xiaoai> {
+        let cookie_store = auth_state.into_cookie_store();
+
+        self.build(Arc::new(CookieStoreMutex::new(cookie_store)))
+    }
+
+    fn api_server(&self) -> crate::Result<Url> {
+        let server = self
+            .region
+            .api_server()
+            .ok_or(crate::Error::UnsupportedRegion(self.region))?;
+
+        Ok(Url::parse(server)?)
+    }
+
+    fn build(self, cookie_store: Arc<CookieStoreMutex>) -> crate::Result<Xiaoai> {
+        let server = self.api_server()?;
+        let user_agent = self.http.api_user_agent();
+        let user_agent = HeaderValue::from_str(&user_agent).map_err(|_| {
+            crate::Error::InvalidInput(format!("无效的 User-Agent `{}`", user_agent.escape_debug()))
+        })?;
+        let (client, attach_cookies) = match self.client {
+            Some(client) => (client, true),
+            // 自定义的 `Transport` 不会经过内部 `Client` 的 Cookies
+            None if self.transport.is_some() => (Client::new(), true),
+            None => {
+                let client = self
+                    .http
+                    .apply(
+                        Client::builder()
+                            .user_agent(user_agent.clone())
+                            .cookie_provider(Arc::clone(&cookie_store)),
+                    )?
+                    .build()?;
+                (client, false)
+            }
+        };
+
+        Ok(Xiaoai {
+            transport: self.transport.unwrap_or_else(|| Arc::new(client.clone())),
+            client,
+            attach_cookies,
+            user_agent,
+            cookie_store,
+            server,
+            timeout: self.timeout,
+            retry: self.retry,
+            rate_limiter: self
+                .rate_limit
+                .map(|(per, burst)| RateLimiter::new(per, burst)),
+            utc_offset: self.utc_offset.unwrap_or(UtcOffset::UTC),
+            device_cache: self.device_cache_ttl.map(DeviceCache::new),
+            muted_volumes: Arc::default(),
+            refresh_hook: None,
+            #[cfg(feature = "test-util")]
+            mock_refresh: self.mock_refresh,
+            refresh_generation: Arc::default(),
+            http: self.http,
+            dry_run: false,
+        })
+    }
+}
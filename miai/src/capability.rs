@@ -0,0 +1,81 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// 设备支持的功能。
+///
+/// 由设备列表中 `capabilities` 的键推断，参见 [`Capabilities`]。
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Capability {
+    /// 播报文本。
+    Tts,
+    /// 播放音乐及播放控制。
+    Playback,
+    /// 闹钟。
+    Alarm,
+    /// 屏幕。
+    Screen,
+}
+
+impl Capability {
+    /// 所有已知的功能。
+    pub const ALL: [Self; 4] = [Self::Tts, Self::Playback, Self::Alarm, Self::Screen];
+
+    /// 表示支持该功能的 `capabilities` 键，有任意一个即视为支持。
+    fn keys(self) -> &'static [&'static str] {
+        match self {
+            Self::Tts => &["tts", "yunduantts"],
+            Self::Playback => &["player_play_url", "player_pause_timer", "lyrics"],
+            Self::Alarm => &["alarm", "classified_alarm", "alarm_repeat_option_v2"],
+            Self::Screen => &["screen", "display"],
+        }
+    }
+}
+
+/// 设备的功能列表，解析自设备列表中的 `capabilities`。
+///
+/// 原始的 `capabilities` 是功能名到 0 或 1 的映射，不同机型的键并不统一。[`Capability`]
+/// 与键的对应关系只是根据观察推断的，可能会有遗漏，其他键可以通过 [`Capabilities::contains_key`] 查询。
+///
+/// # Examples
+///
+/// ```
+/// # use miai::{Capabilities, Capability};
+/// let caps: Capabilities = serde_json::from_str(r#"{"yunduantts": 1, "lyrics": 0}"#).unwrap();
+/// assert!(caps.contains(Capability::Tts));
+/// assert!(!caps.contains(Capability::Playback));
+/// assert!(caps.contains_key("yunduantts"));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Capabilities(BTreeMap<String, Value>);
+
+impl Capabilities {
+    /// 是否支持 `capability`。
+    pub fn contains(&self, capability: Capability) -> bool {
+        capability.keys().iter().any(|key| self.contains_key(key))
+    }
+
+    /// 是否启用了原始的键 `key`，即值不为 0、`false` 或空。
+    pub fn contains_key(&self, key: &str) -> bool {
+        match self.0.get(key) {
+            None | Some(Value::Null) | Some(Value::Bool(false)) => false,
+            Some(Value::Number(n)) => n.as_f64() != Some(0.0),
+            Some(_) => true,
+        }
+    }
+
+    /// 设备没有提供任何功能信息。
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// 支持的所有已知功能。
+    pub fn iter(&self) -> impl Iterator<Item = Capability> + '_ {
+        Capability::ALL
+            .into_iter()
+            .filter(|capability| self.contains(*capability))
+    }
+}
@@ -1,16 +1,101 @@
 //! 小爱对话相关响应体。
 
-use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, ops::BitOr};
+
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::{Map, Value};
-use time::{OffsetDateTime, serde::timestamp::milliseconds};
+use time::{OffsetDateTime, UtcOffset, serde::timestamp::milliseconds};
+
+/// [`Record::primary_answer`] 默认的应答类型偏好，优先大模型的回答，其次是 TTS。
+pub const DEFAULT_ANSWER_PREFERENCE: &[&str] = &["LLM", "TTS"];
 
 /// 表示小爱对话响应体中 `data` 字段的值。
+///
+/// 解析是宽容的，无法解析的记录会被跳过并放入 [`skipped`][Self::skipped]，而不会让整页解析失败。
+///
+/// # Examples
+///
+/// ```
+/// # use miai::conversation::Data;
+/// let data: Data = serde_json::from_value(serde_json::json!({
+///     "records": [
+///         {"query": "你好", "requestId": "1", "time": 0, "answers": null},
+///         {"query": "没有时间"},
+///         null,
+///     ],
+/// }))
+/// .unwrap();
+/// assert_eq!(data.records.len(), 1);
+/// assert!(data.records[0].answers.is_empty());
+/// assert_eq!(data.skipped.len(), 2);
+///
+/// // 没有对话记录时 `records` 可能是 `null`
+/// let empty: Data = serde_json::from_value(serde_json::json!({"records": null})).unwrap();
+/// assert!(empty.records.is_empty());
+/// assert!(empty.next_end_time.is_none());
+/// ```
 #[non_exhaustive]
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Data {
     /// 对话记录。
     pub records: Vec<Record>,
+
+    /// 下一页的游标，即剩余记录中最新一条的时间。
+    ///
+    /// 作为 [`Xiaoai::conversations`][crate::Xiaoai::conversations] 的 `until` 即可获取下一页。
+    #[serde(with = "milliseconds::option")]
+    pub next_end_time: Option<OffsetDateTime>,
+
+    /// 无法解析而被跳过的记录。
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<SkippedRecord>,
+}
+
+impl<'de> Deserialize<'de> for Data {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct RawData {
+            #[serde(default, deserialize_with = "null_as_default")]
+            records: Vec<Value>,
+            #[serde(default, with = "milliseconds::option")]
+            next_end_time: Option<OffsetDateTime>,
+        }
+
+        let raw = RawData::deserialize(deserializer)?;
+        let mut records = Vec::with_capacity(raw.records.len());
+        let mut skipped = Vec::new();
+        for record in raw.records {
+            match Record::deserialize(&record) {
+                Ok(mut parsed) => {
+                    parsed.fill_latency();
+                    records.push(parsed);
+                }
+                Err(err) => skipped.push(SkippedRecord {
+                    raw: record,
+                    error: err.to_string(),
+                }),
+            }
+        }
+
+        Ok(Self {
+            records,
+            next_end_time: raw.next_end_time,
+            skipped,
+        })
+    }
+}
+
+/// 无法解析的对话记录，参见 [`Data::skipped`]。
+#[non_exhaustive]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SkippedRecord {
+    /// 原始的记录。
+    pub raw: Value,
+
+    /// 解析失败的原因。
+    pub error: String,
 }
 
 /// 表示小爱对话的记录。
@@ -20,13 +105,16 @@ pub struct Data {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Record {
-    /// 小爱的应答。
+    /// 小爱的应答，缺失或为 `null` 时为空。
+    #[serde(default, deserialize_with = "null_as_default")]
     pub answers: Vec<Answer>,
 
-    /// 用户的提问。
+    /// 用户的提问，缺失或为 `null` 时为空。
+    #[serde(default, deserialize_with = "null_as_default")]
     pub query: String,
 
-    /// 请求的 ID。
+    /// 请求的 ID，缺失或为 `null` 时为空。
+    #[serde(default, deserialize_with = "null_as_default")]
     pub request_id: String,
 
     /// 记录的时间。
@@ -34,23 +122,362 @@ pub struct Record {
     pub time: OffsetDateTime,
 }
 
+impl Record {
+    /// 换算记录和应答的时间到 `offset`，表示的时刻不变，同 [`OffsetDateTime::to_offset`]。
+    pub fn to_offset(mut self, offset: UtcOffset) -> Self {
+        self.time = self.time.to_offset(offset);
+        for answer in &mut self.answers {
+            answer.answered_at = answer.answered_at.map(|time| time.to_offset(offset));
+        }
+
+        self
+    }
+
+    /// 根据应答的时间补上缺少的耗时，参见 [`Answer::latency_ms`]。
+    fn fill_latency(&mut self) {
+        for answer in &mut self.answers {
+            if answer.latency_ms.is_none()
+                && let Some(answered_at) = answer.answered_at
+            {
+                answer.latency_ms =
+                    u64::try_from((answered_at - self.time).whole_milliseconds()).ok();
+            }
+        }
+    }
+
+    /// 是否有类型为 `kind` 的应答，不区分大小写。
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use miai::conversation::Record;
+    /// fn only_llm(records: Vec<Record>) -> Vec<Record> {
+    ///     records.into_iter().filter(|record| record.has_kind("llm")).collect()
+    /// }
+    /// ```
+    pub fn has_kind(&self, kind: &str) -> bool {
+        self.answers
+            .iter()
+            .any(|answer| answer.kind.eq_ignore_ascii_case(kind))
+    }
+
+    /// 最主要的应答，只考虑有 [`Answer::display_text`] 的应答。
+    ///
+    /// 按 `preference` 的顺序查找第一个类型匹配的应答，类型不区分大小写；都不匹配时返回第一个有文本的应答。
+    /// 通常使用 [`DEFAULT_ANSWER_PREFERENCE`]，即大模型的回答优先于 TTS。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use miai::conversation::{DEFAULT_ANSWER_PREFERENCE, Record};
+    /// let record: Record = serde_json::from_value(serde_json::json!({
+    ///     "query": "你是谁",
+    ///     "requestId": "1",
+    ///     "time": 0,
+    ///     "answers": [
+    ///         {"type": "TTS", "tts": {"text": "我是小爱"}},
+    ///         {"type": "LLM", "llm": {"text": "我是小爱同学，你的智能助手"}},
+    ///     ],
+    /// }))
+    /// .unwrap();
+    ///
+    /// let answer = record.primary_answer(DEFAULT_ANSWER_PREFERENCE).unwrap();
+    /// assert_eq!(answer.kind, "LLM");
+    ///
+    /// let answer = record.primary_answer(&["tts"]).unwrap();
+    /// assert_eq!(answer.kind, "TTS");
+    ///
+    /// // 都不匹配时返回第一个
+    /// let answer = record.primary_answer(&["music"]).unwrap();
+    /// assert_eq!(answer.kind, "TTS");
+    /// ```
+    pub fn primary_answer(&self, preference: &[impl AsRef<str>]) -> Option<&Answer> {
+        let mut readable = self
+            .answers
+            .iter()
+            .filter(|answer| answer.display_text().is_some());
+        preference
+            .iter()
+            .find_map(|kind| {
+                readable
+                    .clone()
+                    .find(|answer| answer.kind.eq_ignore_ascii_case(kind.as_ref()))
+            })
+            .or_else(|| readable.next())
+    }
+}
+
 /// 表示小爱对话记录的应答。
+///
+/// # Examples
+///
+/// ```
+/// # use miai::conversation::Data;
+/// let data: Data = serde_json::from_value(serde_json::json!({
+///     "records": [{
+///         "query": "你是谁",
+///         "requestId": "1",
+///         "time": 1_700_000_000_000u64,
+///         "answers": [{"type": "LLM", "llm": {"text": "我是小爱"}, "time": 1_700_000_001_500u64}],
+///     }],
+/// }))
+/// .unwrap();
+/// let answer = &data.records[0].answers[0];
+/// assert!(answer.answered_at.is_some());
+/// assert_eq!(answer.latency_ms, Some(1500));
+///
+/// // 同时带有多个时间字段时取第一个，不会让记录解析失败
+/// let data: Data = serde_json::from_value(serde_json::json!({
+///     "records": [{
+///         "query": "你是谁",
+///         "requestId": "1",
+///         "time": 1_700_000_000_000u64,
+///         "answers": [{
+///             "type": "LLM",
+///             "llm": {"text": "我是小爱"},
+///             "time": 1_700_000_001_500u64,
+///             "timestamp": 1_700_000_002_000u64,
+///             "latencyMs": 1200,
+///             "latency": 1300,
+///         }],
+///     }],
+/// }))
+/// .unwrap();
+/// assert!(data.skipped.is_empty());
+/// assert_eq!(data.records[0].answers[0].latency_ms, Some(1200));
+/// ```
 #[non_exhaustive]
 #[derive(Clone, Debug, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", from = "RawAnswer")]
 pub struct Answer {
     /// 应答的类型。
     #[serde(rename = "type")]
     pub kind: String,
 
-    // 为了实现 payload 而尝试捕获该值
+    // 为了实现 payload 而尝试捕获该值，解码后通过 `flags` 暴露
     bit_set: Option<Vec<u8>>,
 
+    /// 应答的时间，只有原始应答中带有毫秒时间戳 `time` 或 `timestamp` 时才有。
+    #[serde(
+        serialize_with = "milliseconds::option::serialize",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub answered_at: Option<OffsetDateTime>,
+
+    /// 从提问到应答的耗时，单位为毫秒。
+    ///
+    /// 原始应答中带有 `latencyMs` 或 `latency` 时直接使用，否则在解析 [`Data`] 时根据
+    /// [`Self::answered_at`] 与 [`Record::time`] 计算。两者都没有时为 `None`。
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+
     /// 应答的有效数据。
     #[serde(flatten)]
     pub payload: AnswerPayload,
 }
 
+/// 原始的应答，同一含义的字段可能以不同的名称同时出现，按顺序取第一个有效的。
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawAnswer {
+    #[serde(rename = "type")]
+    kind: String,
+    bit_set: Option<Vec<u8>>,
+    // 序列化后的名称
+    #[serde(default, deserialize_with = "lenient_millis")]
+    answered_at: Option<OffsetDateTime>,
+    #[serde(default, deserialize_with = "lenient_millis")]
+    time: Option<OffsetDateTime>,
+    #[serde(default, deserialize_with = "lenient_millis")]
+    timestamp: Option<OffsetDateTime>,
+    #[serde(default, deserialize_with = "lenient_u64")]
+    latency_ms: Option<u64>,
+    #[serde(default, deserialize_with = "lenient_u64")]
+    latency: Option<u64>,
+    #[serde(flatten)]
+    payload: AnswerPayload,
+}
+
+impl From<RawAnswer> for Answer {
+    fn from(raw: RawAnswer) -> Self {
+        Self {
+            kind: raw.kind,
+            bit_set: raw.bit_set,
+            answered_at: raw.answered_at.or(raw.time).or(raw.timestamp),
+            latency_ms: raw.latency_ms.or(raw.latency),
+            payload: raw.payload,
+        }
+    }
+}
+
+impl Answer {
+    /// 应答的标志位，解码自原始的 `bitSet` 字段。
+    pub fn flags(&self) -> AnswerFlags {
+        self.bit_set
+            .as_deref()
+            .map(AnswerFlags::from_bit_set)
+            .unwrap_or_default()
+    }
+
+    /// 原始的 `bitSet` 字段。
+    pub fn bit_set(&self) -> Option<&[u8]> {
+        self.bit_set.as_deref()
+    }
+
+    /// 最适合展示给人看的文本，没有可读的文本时返回 `None`。
+    ///
+    /// - TTS 和 LLM 返回应答的文本。
+    /// - 音乐返回“歌曲 - 歌手”，只有其中之一时返回该项。
+    /// - 天气返回地点、天气状况和当前温度，以空格分隔。
+    /// - 未知的类型会尝试常见的结构，比如 `text`、`content` 或 `displayText` 字段，
+    ///   以及嵌套一层的同名字段，如 `{"tts": {"text": "..."}}`。
+    ///
+    /// 空白的文本视为没有文本。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use miai::conversation::Answer;
+    /// let display_text = |answer: serde_json::Value| {
+    ///     let answer: Answer = serde_json::from_value(answer).unwrap();
+    ///     answer.display_text().map(|text| text.into_owned())
+    /// };
+    ///
+    /// let tts = serde_json::json!({"type": "TTS", "tts": {"text": "你好"}});
+    /// assert_eq!(display_text(tts).as_deref(), Some("你好"));
+    ///
+    /// let llm = serde_json::json!({"type": "LLM", "llm": {"text": "我是小爱"}});
+    /// assert_eq!(display_text(llm).as_deref(), Some("我是小爱"));
+    ///
+    /// let music = serde_json::json!({"type": "MUSIC", "music": {"title": "晴天", "artist": "周杰伦"}});
+    /// assert_eq!(display_text(music).as_deref(), Some("晴天 - 周杰伦"));
+    ///
+    /// let weather = serde_json::json!({
+    ///     "type": "WEATHER",
+    ///     "weather": {"city": "北京", "weather": "晴", "temperature": 25}
+    /// });
+    /// assert_eq!(display_text(weather).as_deref(), Some("北京 晴 25"));
+    ///
+    /// let unknown = serde_json::json!({"type": "JOKE", "joke": {"content": "一个笑话"}});
+    /// assert_eq!(display_text(unknown).as_deref(), Some("一个笑话"));
+    ///
+    /// let empty = serde_json::json!({"type": "TTS", "tts": {"text": " "}});
+    /// assert_eq!(display_text(empty), None);
+    ///
+    /// let nothing = serde_json::json!({"type": "MUSIC", "music": {}});
+    /// assert_eq!(display_text(nothing), None);
+    /// ```
+    pub fn display_text(&self) -> Option<Cow<'_, str>> {
+        let non_empty = |text: &str| !text.trim().is_empty();
+        match &self.payload {
+            AnswerPayload::Tts { text } | AnswerPayload::Llm { text } => {
+                Some(Cow::Borrowed(text.as_str())).filter(|text| non_empty(text))
+            }
+            AnswerPayload::Music { title, artist, .. } => {
+                join_non_empty([title, artist], " - ").map(Cow::Owned)
+            }
+            AnswerPayload::Weather {
+                location,
+                current_temp,
+                condition,
+                ..
+            } => join_non_empty([location, condition, current_temp], " ").map(Cow::Owned),
+            AnswerPayload::Unknown(payload) => {
+                let nested = payload.values().filter_map(Value::as_object);
+                [payload]
+                    .into_iter()
+                    .chain(nested)
+                    .find_map(|object| {
+                        TEXT_KEYS
+                            .iter()
+                            .filter_map(|key| object.get(*key)?.as_str())
+                            .find(|text| non_empty(text))
+                    })
+                    .map(Cow::Borrowed)
+            }
+        }
+    }
+}
+
+/// 未知类型的应答中可能存放文本的字段。
+const TEXT_KEYS: [&str; 3] = ["text", "content", "displayText"];
+
+/// 以 `separator` 连接非空的部分，全部为空时返回 `None`。
+fn join_non_empty<const N: usize>(parts: [&Option<String>; N], separator: &str) -> Option<String> {
+    let parts: Vec<_> = parts
+        .into_iter()
+        .flatten()
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty())
+        .collect();
+
+    (!parts.is_empty()).then(|| parts.join(separator))
+}
+
+/// 小爱对话记录应答的标志位。
+///
+/// 原始的 `bitSet` 是由 0 和 1 组成的数组，第 n 个元素为 1 即表示第 n 位被设置。
+/// 目前观察到的取值通常是 `[0, 1, 1]`：
+///
+/// - 第 0 位：未见被设置过。
+/// - 第 1 位：[`AnswerFlags::HAS_PAYLOAD`]，应答带有与 `type` 对应的有效数据。
+/// - 第 2 位：[`AnswerFlags::HAS_TYPE`]，应答带有 `type`。
+///
+/// 这些含义只是根据观察推断的，更多的位可以通过 [`AnswerFlags::contains_bit`] 查询。
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct AnswerFlags(u64);
+
+impl AnswerFlags {
+    /// 应答带有与 `type` 对应的有效数据。
+    pub const HAS_PAYLOAD: Self = Self(1 << 1);
+    /// 应答带有 `type`。
+    pub const HAS_TYPE: Self = Self(1 << 2);
+
+    /// 从原始的 `bitSet` 解码，超过 64 位的部分会被忽略。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use miai::conversation::AnswerFlags;
+    /// let flags = AnswerFlags::from_bit_set(&[0, 1, 1]);
+    /// assert!(flags.contains(AnswerFlags::HAS_PAYLOAD | AnswerFlags::HAS_TYPE));
+    /// assert!(!flags.contains_bit(0));
+    /// ```
+    pub fn from_bit_set(bit_set: &[u8]) -> Self {
+        let bits = bit_set
+            .iter()
+            .take(64)
+            .enumerate()
+            .filter(|(_, bit)| **bit != 0)
+            .fold(0, |bits, (i, _)| bits | 1 << i);
+
+        Self(bits)
+    }
+
+    /// 所有位组成的整数。
+    pub fn bits(self) -> u64 {
+        self.0
+    }
+
+    /// 是否设置了 `other` 中的所有位。
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// 是否设置了第 `index` 位。
+    pub fn contains_bit(self, index: u32) -> bool {
+        index < u64::BITS && self.0 & 1 << index != 0
+    }
+}
+
+impl BitOr for AnswerFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
 /// 表示小爱对话记录应答的有效数据。
 #[non_exhaustive]
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -68,7 +495,113 @@ pub enum AnswerPayload {
         /// 应答的文本。
         text: String,
     },
+    /// 类型为 MUSIC。
+    ///
+    /// 各字段在不同的应答中时有时无。
+    #[non_exhaustive]
+    Music {
+        /// 歌曲名称。
+        title: Option<String>,
+        /// 歌手。
+        artist: Option<String>,
+        /// 专辑。
+        album: Option<String>,
+        /// 封面图片的链接。
+        #[serde(alias = "cover")]
+        cover_url: Option<String>,
+    },
+    /// 类型为 WEATHER。
+    ///
+    /// 各字段在不同的应答中时有时无。
+    #[non_exhaustive]
+    Weather {
+        /// 查询的地点。
+        #[serde(alias = "city")]
+        location: Option<String>,
+        /// 当前温度。
+        #[serde(
+            default,
+            alias = "temperature",
+            alias = "temp",
+            deserialize_with = "string_or_number"
+        )]
+        current_temp: Option<String>,
+        /// 天气状况，比如“晴”。
+        #[serde(alias = "weather")]
+        condition: Option<String>,
+        /// 未来几天的预报。
+        #[serde(default)]
+        forecast: Vec<Forecast>,
+    },
     /// 未知的类型。
     #[serde(untagged)] // https://github.com/serde-rs/serde/issues/912#issuecomment-1868785603
     Unknown(Map<String, Value>),
 }
+
+/// 天气应答中一天的预报。
+///
+/// 各字段在不同的应答中时有时无。
+#[non_exhaustive]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Forecast {
+    /// 日期。
+    pub date: Option<String>,
+    /// 天气状况。
+    #[serde(alias = "weather")]
+    pub condition: Option<String>,
+    /// 最高温度。
+    #[serde(
+        default,
+        alias = "tempHigh",
+        alias = "high",
+        deserialize_with = "string_or_number"
+    )]
+    pub max_temp: Option<String>,
+    /// 最低温度。
+    #[serde(
+        default,
+        alias = "tempLow",
+        alias = "low",
+        deserialize_with = "string_or_number"
+    )]
+    pub min_temp: Option<String>,
+}
+
+/// 把 `null` 视为默认值。
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// 数字有时是字符串，格式不对时视为没有，而不是让整条记录解析失败。
+fn lenient_u64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    Ok(match Option::<Value>::deserialize(deserializer)? {
+        Some(Value::Number(number)) => number.as_u64(),
+        Some(Value::String(number)) => number.trim().parse().ok(),
+        _ => None,
+    })
+}
+
+/// 毫秒时间戳，同 [`lenient_u64`]。
+fn lenient_millis<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<OffsetDateTime>, D::Error> {
+    Ok(lenient_u64(deserializer)?.and_then(|millis| {
+        OffsetDateTime::from_unix_timestamp_nanos(i128::from(millis) * 1_000_000).ok()
+    }))
+}
+
+/// 温度等字段有时是字符串，有时是数字，统一为字符串。
+fn string_or_number<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    Ok(match Option::<Value>::deserialize(deserializer)? {
+        Some(Value::String(s)) => Some(s),
+        Some(Value::Number(n)) => Some(n.to_string()),
+        _ => None,
+    })
+}
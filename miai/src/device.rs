@@ -0,0 +1,187 @@
+use std::{path::Path, time::Duration};
+
+use crate::{
+    NlpResult, PlayMode, PlayState, PlayerStatus, Xiaoai, XiaoaiResponse, conversation::Record,
+};
+
+/// 绑定了设备 ID 的 [`Xiaoai`]，通过 [`Xiaoai::device`] 或 [`Xiaoai::devices`] 获得。
+///
+/// 只是对 `Xiaoai` 中常用方法的简单转发，省去每次传入设备 ID，其余的方法可以通过 [`Device::xiaoai`]
+/// 和 [`Device::id`] 调用。
+///
+/// # Examples
+///
+/// ```no_run
+/// # async fn device(xiaoai: miai::Xiaoai) -> miai::Result<()> {
+/// for device in xiaoai.devices().await? {
+///     device.say("你好！").await?;
+///     println!("{} 的音量: {}", device.id(), device.volume().await?);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Device<'a> {
+    xiaoai: &'a Xiaoai,
+    id: String,
+}
+
+impl<'a> Device<'a> {
+    pub(crate) fn new(xiaoai: &'a Xiaoai, id: String) -> Self {
+        Self { xiaoai, id }
+    }
+
+    /// 设备 ID。
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// 所属的 [`Xiaoai`]。
+    pub fn xiaoai(&self) -> &'a Xiaoai {
+        self.xiaoai
+    }
+
+    /// 同 [`Xiaoai::tts`]。
+    pub async fn say(&self, text: &str) -> crate::Result<XiaoaiResponse> {
+        self.xiaoai.tts(&self.id, text).await
+    }
+
+    /// 同 [`Xiaoai::tts_chunked`]。
+    pub async fn say_chunked(&self, text: &str) -> crate::Result<Vec<XiaoaiResponse>> {
+        self.xiaoai.tts_chunked(&self.id, text).await
+    }
+
+    /// 同 [`Xiaoai::play_url`]。
+    pub async fn play_url(&self, url: &str) -> crate::Result<XiaoaiResponse> {
+        self.xiaoai.play_url(&self.id, url).await
+    }
+
+    /// 同 [`Xiaoai::play_file`]。
+    pub async fn play_file(
+        &self,
+        path: impl AsRef<Path>,
+        timeout: Duration,
+    ) -> crate::Result<XiaoaiResponse> {
+        self.xiaoai.play_file(&self.id, path, timeout).await
+    }
+
+    /// 继续播放，同 [`Xiaoai::set_play_state`] 传入 [`PlayState::Play`]。
+    pub async fn play(&self) -> crate::Result<XiaoaiResponse> {
+        self.xiaoai.set_play_state(&self.id, PlayState::Play).await
+    }
+
+    /// 暂停，同 [`Xiaoai::set_play_state`] 传入 [`PlayState::Pause`]。
+    pub async fn pause(&self) -> crate::Result<XiaoaiResponse> {
+        self.xiaoai.set_play_state(&self.id, PlayState::Pause).await
+    }
+
+    /// 停止播放和播报，同 [`Xiaoai::stop_all`]。
+    pub async fn stop(&self) -> crate::Result<XiaoaiResponse> {
+        self.xiaoai.stop_all(&self.id).await
+    }
+
+    /// 同 [`Xiaoai::next_track`]。
+    pub async fn next_track(&self) -> crate::Result<XiaoaiResponse> {
+        self.xiaoai.next_track(&self.id).await
+    }
+
+    /// 同 [`Xiaoai::prev_track`]。
+    pub async fn prev_track(&self) -> crate::Result<XiaoaiResponse> {
+        self.xiaoai.prev_track(&self.id).await
+    }
+
+    /// 同 [`Xiaoai::get_volume`]。
+    pub async fn volume(&self) -> crate::Result<u32> {
+        self.xiaoai.get_volume(&self.id).await
+    }
+
+    /// 同 [`Xiaoai::max_volume`]。
+    pub async fn max_volume(&self) -> crate::Result<u32> {
+        self.xiaoai.max_volume(&self.id).await
+    }
+
+    /// 同 [`Xiaoai::set_volume`]。
+    pub async fn set_volume(&self, volume: u32) -> crate::Result<XiaoaiResponse> {
+        self.xiaoai.set_volume(&self.id, volume).await
+    }
+
+    /// 同 [`Xiaoai::set_volume_verified`]。
+    pub async fn set_volume_verified(&self, volume: u32) -> crate::Result<XiaoaiResponse> {
+        self.xiaoai.set_volume_verified(&self.id, volume).await
+    }
+
+    /// 同 [`Xiaoai::adjust_volume`]。
+    pub async fn adjust_volume(&self, delta: i32) -> crate::Result<u32> {
+        self.xiaoai.adjust_volume(&self.id, delta).await
+    }
+
+    /// 同 [`Xiaoai::ramp_volume`]。
+    pub async fn ramp_volume(&self, target: u32, over: Duration, steps: u32) -> crate::Result<u32> {
+        self.xiaoai.ramp_volume(&self.id, target, over, steps).await
+    }
+
+    /// 同 [`Xiaoai::mute`]。
+    pub async fn mute(&self) -> crate::Result<u32> {
+        self.xiaoai.mute(&self.id).await
+    }
+
+    /// 同 [`Xiaoai::unmute`]。
+    pub async fn unmute(&self) -> crate::Result<u32> {
+        self.xiaoai.unmute(&self.id).await
+    }
+
+    /// 同 [`Xiaoai::player_status`]。
+    pub async fn status(&self) -> crate::Result<PlayerStatus> {
+        self.xiaoai.player_status(&self.id).await
+    }
+
+    /// 同 [`Xiaoai::play_mode`]。
+    pub async fn play_mode(&self) -> crate::Result<PlayMode> {
+        self.xiaoai.play_mode(&self.id).await
+    }
+
+    /// 同 [`Xiaoai::set_play_mode`]。
+    pub async fn set_play_mode(&self, mode: PlayMode) -> crate::Result<XiaoaiResponse> {
+        self.xiaoai.set_play_mode(&self.id, mode).await
+    }
+
+    /// 同 [`Xiaoai::nlp`]。
+    pub async fn nlp(&self, text: &str) -> crate::Result<XiaoaiResponse> {
+        self.xiaoai.nlp(&self.id, text).await
+    }
+
+    /// 同 [`Xiaoai::execute_silent`]。
+    pub async fn execute_silent(&self, text: &str) -> crate::Result<XiaoaiResponse> {
+        self.xiaoai.execute_silent(&self.id, text).await
+    }
+
+    /// 同 [`Xiaoai::nlp_parsed`]。
+    pub async fn nlp_parsed(&self, text: &str) -> crate::Result<NlpResult> {
+        self.xiaoai.nlp_parsed(&self.id, text).await
+    }
+
+    /// 同 [`Xiaoai::ask_and_wait`]。
+    pub async fn ask(&self, text: &str, timeout: Duration) -> crate::Result<Record> {
+        self.xiaoai.ask_and_wait(&self.id, text, timeout).await
+    }
+
+    /// 同 [`Xiaoai::play_music_search`]。
+    pub async fn play_music_search(&self, query: &str) -> crate::Result<Option<Record>> {
+        self.xiaoai.play_music_search(&self.id, query).await
+    }
+
+    /// 同 [`Xiaoai::ping`]。
+    pub async fn ping(&self) -> crate::Result<()> {
+        self.xiaoai.ping(&self.id).await
+    }
+
+    /// 同 [`Xiaoai::ubus_call`]。
+    pub async fn ubus_call(
+        &self,
+        path: &str,
+        method: &str,
+        message: &str,
+    ) -> crate::Result<XiaoaiResponse> {
+        self.xiaoai.ubus_call(&self.id, path, method, message).await
+    }
+}
@@ -0,0 +1,101 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tokio::sync::Mutex;
+use tracing::debug;
+
+use crate::DeviceInfo;
+
+/// 获取的时间和设备列表。
+type Entry = (Instant, Vec<DeviceInfo>);
+
+/// 设备列表的缓存，超过 `ttl` 后失效。
+///
+/// 获取期间会一直持有锁，因此并发的调用者会等待同一个请求完成，而不是各自发出请求。
+/// 克隆后共享同一份缓存。
+#[derive(Clone, Debug)]
+pub(crate) struct DeviceCache {
+    ttl: Duration,
+    entry: Arc<Mutex<Option<Entry>>>,
+}
+
+impl DeviceCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entry: Arc::default(),
+        }
+    }
+
+    /// 返回未过期的缓存，否则调用 `fetch` 获取并缓存。`fetch` 出错时不会缓存。
+    pub async fn get_or_fetch<F, Fut>(&self, fetch: F) -> crate::Result<Vec<DeviceInfo>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = crate::Result<Vec<DeviceInfo>>>,
+    {
+        let mut entry = self.entry.lock().await;
+        if let Some((_, devices)) = entry
+            .as_ref()
+            .filter(|(fetched_at, _)| fetched_at.elapsed() < self.ttl)
+        {
+            return Ok(devices.clone());
+        }
+
+        debug!("设备列表缓存失效，重新获取");
+        let devices = fetch().await?;
+        *entry = Some((Instant::now(), devices.clone()));
+
+        Ok(devices)
+    }
+
+    /// 不发出请求，只返回未过期的缓存。正在获取时返回 `None`，而不是等待获取完成。
+    pub fn peek(&self) -> Option<Vec<DeviceInfo>> {
+        let entry = self.entry.try_lock().ok()?;
+        entry
+            .as_ref()
+            .filter(|(fetched_at, _)| fetched_at.elapsed() < self.ttl)
+            .map(|(_, devices)| devices.clone())
+    }
+
+    /// 使缓存失效。
+    pub async fn invalidate(&self) {
+        *self.entry.lock().await = None;
+    }
+}
+
+/// 保存在文件中的设备列表，参见 [`Xiaoai::cache_device_info`][crate::Xiaoai::cache_device_info]。
+///
+/// 设备列表很少变化，可以在无法联网或不想发出请求时代替 [`Xiaoai::device_info`][crate::Xiaoai::device_info]，
+/// 但需要留意它的时效，比如通过 [`Self::age`] 判断是否过旧。
+#[non_exhaustive]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedDeviceInfo {
+    /// 获取设备列表的时间。
+    #[serde(with = "time::serde::timestamp")]
+    pub fetched_at: OffsetDateTime,
+
+    /// 设备列表。
+    pub devices: Vec<DeviceInfo>,
+}
+
+impl CachedDeviceInfo {
+    /// 以当前时间创建。
+    pub fn new(devices: Vec<DeviceInfo>) -> Self {
+        Self {
+            fetched_at: OffsetDateTime::now_utc(),
+            devices,
+        }
+    }
+
+    /// 距离获取设备列表过去的时间，系统时间被调回时为 0。
+    pub fn age(&self) -> Duration {
+        (OffsetDateTime::now_utc() - self.fetched_at)
+            .try_into()
+            .unwrap_or_default()
+    }
+}
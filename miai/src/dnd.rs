@@ -0,0 +1,31 @@
+//! 小爱勿扰模式相关类型。
+
+use serde::{Deserialize, Serialize};
+use time::Time;
+
+use crate::alarm::hh_mm;
+
+/// 勿扰模式的设置。
+///
+/// 时间是设备本地时区的时间，`start` 与 `end` 相同时表示全天勿扰，`end` 早于 `start` 时表示跨越午夜。
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Dnd {
+    /// 是否启用。
+    #[serde(alias = "enable")]
+    pub enabled: bool,
+
+    /// 开始时间，精确到分钟。
+    #[serde(with = "hh_mm")]
+    pub start: Time,
+
+    /// 结束时间，精确到分钟。
+    #[serde(with = "hh_mm")]
+    pub end: Time,
+}
+
+impl Dnd {
+    /// 是否为全天勿扰。
+    pub fn is_all_day(&self) -> bool {
+        self.start == self.end
+    }
+}
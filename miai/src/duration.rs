@@ -0,0 +1,100 @@
+use std::time::Duration;
+
+/// 解析时长，供定时停止、闹钟等需要时长的地方统一使用。
+///
+/// 支持以下格式：
+///
+/// - 带单位的数字，单位可以是 `d`、`h`、`m`、`s`，缺省时为秒，如 `30m`、`90`；
+/// - 多个单位组合，需要从大到小且不重复，如 `1h30m`、`2d12h`；
+/// - 冒号分隔的 `M:SS` 或 `H:MM:SS`，除第一段外不能超过 59，如 `1:30`、`1:00:00`。
+///
+/// 首尾的空白会被忽略。
+///
+/// # Errors
+///
+/// 格式无效时返回 [`Error::InvalidInput`][crate::Error::InvalidInput]，比如空字符串、未知的单位、
+/// 单位重复或顺序颠倒，以及组合中缺少单位的数字（如 `1h30`，无法确定是分钟还是秒）。
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use miai::parse_duration;
+///
+/// assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+/// assert_eq!(parse_duration("30m").unwrap(), Duration::from_secs(30 * 60));
+/// assert_eq!(parse_duration("1h30m").unwrap(), Duration::from_secs(90 * 60));
+/// assert_eq!(parse_duration("1:30").unwrap(), Duration::from_secs(90));
+/// assert_eq!(parse_duration("1:00:00").unwrap(), Duration::from_secs(60 * 60));
+///
+/// assert!(parse_duration("").is_err());
+/// assert!(parse_duration("1h30").is_err());
+/// assert!(parse_duration("30m1h").is_err());
+/// assert!(parse_duration("1:90").is_err());
+/// ```
+pub fn parse_duration(s: &str) -> crate::Result<Duration> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Err(crate::Error::InvalidInput("时长不能为空".into()));
+    }
+
+    let secs = if s.contains(':') {
+        parse_colon(s)
+    } else {
+        parse_units(s)
+    };
+    secs.map(Duration::from_secs).ok_or_else(|| {
+        crate::Error::InvalidInput(format!(
+            "无效的时长 `{s}`，应为 30m、1h30m、90（秒）或 1:30 这样的格式"
+        ))
+    })
+}
+
+/// 解析 `M:SS` 或 `H:MM:SS`。
+fn parse_colon(s: &str) -> Option<u64> {
+    let parts = s
+        .split(':')
+        .map(|part| {
+            (!part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()))
+                .then(|| part.parse::<u64>().ok())
+                .flatten()
+        })
+        .collect::<Option<Vec<_>>>()?;
+    if !(2..=3).contains(&parts.len()) || parts[1..].iter().any(|&part| part >= 60) {
+        return None;
+    }
+
+    parts
+        .into_iter()
+        .try_fold(0u64, |secs, part| secs.checked_mul(60)?.checked_add(part))
+}
+
+/// 解析带单位的数字及其组合。
+fn parse_units(s: &str) -> Option<u64> {
+    const UNITS: [(char, u64); 4] = [('d', 24 * 60 * 60), ('h', 60 * 60), ('m', 60), ('s', 1)];
+
+    // 没有单位时为秒
+    if s.bytes().all(|b| b.is_ascii_digit()) {
+        return s.parse().ok();
+    }
+
+    let mut secs = 0u64;
+    let mut rest = s;
+    // 下一个单位在 `UNITS` 中最小的下标，用于保证从大到小且不重复
+    let mut next_unit = 0;
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+        let number: u64 = rest[..digits].parse().ok()?;
+        let unit = rest[digits..].chars().next()?;
+        let index = UNITS[next_unit..]
+            .iter()
+            .position(|&(name, _)| name == unit)?
+            + next_unit;
+        secs = secs.checked_add(number.checked_mul(UNITS[index].1)?)?;
+        next_unit = index + 1;
+        rest = &rest[digits + unit.len_utf8()..];
+    }
+
+    Some(secs)
+}
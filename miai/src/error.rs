@@ -1,21 +1,126 @@
-use crate::XiaoaiResponse;
+use crate::login::LoginChallenge;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// `miai` 中所有可能出现的错误。
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
-    #[error("API 返回 {}: {}", .0.code, .0.message)]
-    Api(XiaoaiResponse),
+    /// 服务端返回了非 0 的错误码，参见 [`XiaoaiResponse::error_for_code`][crate::XiaoaiResponse::error_for_code]。
+    #[error("{message}（错误码 {code}）")]
+    Api { code: i64, message: String },
+
+    /// 登录时认证失败，比如账号或密码错误。
+    #[error("认证失败 {code}: {message}")]
+    Auth { code: i64, message: String },
+
+    /// 密码登录时需要额外的短信或邮箱验证，参见 [`Login::start_verification`][crate::login::Login::start_verification]。
+    #[error("需要进行身份验证")]
+    VerificationRequired(LoginChallenge),
+
+    /// 扫码登录时，二维码在有效期内没有被扫描确认。
+    #[error("二维码已过期，请重新获取")]
+    QrCodeExpired,
 
     #[error(transparent)]
-    Reqwest(#[from] reqwest::Error),
+    Reqwest(reqwest::Error),
+
+    /// 请求超时，参见 [`XiaoaiBuilder::timeout`][crate::XiaoaiBuilder::timeout]。
+    #[error("请求超时")]
+    Timeout,
 
     #[error(transparent)]
     Json(#[from] serde_json::Error),
 
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
     #[error(transparent)]
     Cookie(#[from] cookie_store::CookieError),
 
     #[error(transparent)]
     Url(#[from] url::ParseError),
+
+    /// 认证文件的版本不受支持，可能是由更新或过旧的版本保存的，需要重新登录。
+    ///
+    /// 缺少版本号的认证文件视为版本 0。
+    #[error("不支持的认证文件版本 {0}")]
+    UnsupportedAuthVersion(u32),
+
+    /// 找不到指定 ID 的设备。
+    #[error("找不到设备 `{0}`")]
+    DeviceNotFound(String),
+
+    /// 传入的是设备的 miio did，而不是小爱服务使用的设备 ID，参见 [`DeviceInfo::miio_did`][crate::DeviceInfo::miio_did]。
+    #[error("`{did}` 是设备的 miio did，请改用设备 ID `{device_id}`")]
+    MiioDidGiven { did: String, device_id: String },
+
+    /// 传入的参数无效。
+    #[error("参数无效: {0}")]
+    InvalidInput(String),
+
+    /// 音量超出了 0 到 [`MAX_VOLUME`][crate::MAX_VOLUME] 的范围。
+    #[error("音量 {0} 超出范围，需要在 0 到 {max} 之间", max = crate::MAX_VOLUME)]
+    InvalidVolume(u32),
+
+    /// 设置音量后读回的音量与设置的不一致，参见 [`Xiaoai::set_volume_verified`][crate::Xiaoai::set_volume_verified]。
+    #[error("音量没有生效，期望 {expected}，实际为 {actual}")]
+    VolumeNotApplied { expected: u32, actual: u32 },
+
+    /// 响应的数据不符合预期，可能是机型或 API 发生了变化。
+    #[error("响应不符合预期: {0}")]
+    UnexpectedResponse(serde_json::Value),
+
+    /// 播放器当前没有在播放，无法执行相应的操作。
+    #[error("当前没有正在播放的内容")]
+    NotPlaying,
+
+    /// 设备不支持该操作，或设置没有生效。
+    #[error("设备不支持{0}")]
+    Unsupported(String),
+
+    /// 该地区没有小爱服务，参见 [`Region`][crate::Region]。
+    #[error("地区 `{0}` 没有小爱服务，目前只支持 `cn`")]
+    UnsupportedRegion(crate::Region),
+
+    /// 登录状态已过期，且无法自动刷新，需要重新登录。
+    #[error("登录状态已过期，请重新登录")]
+    SessionExpired,
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            Self::Timeout
+        } else {
+            Self::Reqwest(err)
+        }
+    }
+}
+
+impl Error {
+    /// 是否是因为设备离线而失败。
+    ///
+    /// 服务端没有为此提供专门的错误码，只能根据 [`Error::Api`] 的消息判断。
+    pub fn is_device_offline(&self) -> bool {
+        match self {
+            Self::Api { message, .. } => {
+                message.contains("离线") || message.to_ascii_lowercase().contains("offline")
+            }
+            _ => false,
+        }
+    }
+
+    /// 是否是暂时性的错误，即网络错误、服务端 5xx 错误或空响应体等，重试可能会成功。
+    ///
+    /// 客户端错误和登录失效不属于此类。
+    pub(crate) fn is_transient(&self) -> bool {
+        match self {
+            Self::Timeout => true,
+            Self::Reqwest(err) => match err.status() {
+                Some(status) => status.is_server_error(),
+                None => err.is_connect() || err.is_request() || err.is_body() || err.is_decode(),
+            },
+            _ => false,
+        }
+    }
 }
@@ -10,6 +10,14 @@
 //! - 控制播放状态。
 //! - 执行文本（询问小爱）。
 //! - 查询对话记录。
+//! - 管理闹钟。
+//! - 设置勿扰模式。
+//! - 读写 MIoT 属性。
+//!
+//! # 特性
+//!
+//! - `test-util`：提供模拟小爱服务的 `MockTransport`，用于在测试中代替真实的服务端，参见 [`Transport`]；
+//!   以及模拟刷新登录状态的 `XiaoaiBuilder::mock_refresh`。
 //!
 //! # 示例
 //!
@@ -29,9 +37,28 @@
 //! }
 //! ```
 
+pub mod alarm;
+mod auth_file;
+mod builder;
+mod capability;
 pub mod conversation;
+mod device;
+mod device_cache;
+pub mod dnd;
+mod duration;
 mod error;
 pub mod login;
+pub mod miot;
+mod model;
+mod nlp;
+mod player;
+mod rate_limit;
+mod redact;
+mod region;
+mod retry;
+mod serve;
+mod transport;
+mod ubus;
 mod util;
 mod xiaoai;
 
@@ -43,7 +70,19 @@ pub mod json {
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use serde_json::Value;
 
+pub use auth_file::AuthState;
+pub use builder::*;
+pub use capability::*;
+pub use device::*;
+pub use device_cache::CachedDeviceInfo;
+pub use duration::parse_duration;
 pub use error::*;
+pub use model::*;
+pub use nlp::*;
+pub use player::*;
+pub use region::*;
+pub use transport::*;
+pub use ubus::*;
 pub use xiaoai::*;
 
 /// 小爱服务请求的响应。
@@ -80,6 +119,7 @@ impl XiaoaiResponse {
     /// fn on_response(res: XiaoaiResponse) {
     ///     match res.error_for_code() {
     ///         Ok(res) => assert_eq!(res.code, 0),
+    ///         Err(err) if err.is_device_offline() => eprintln!("设备离线: {err}"),
     ///         Err(_err) => ()
     ///     }
     /// }
@@ -88,7 +128,10 @@ impl XiaoaiResponse {
         if self.code == 0 {
             Ok(self)
         } else {
-            Err(crate::Error::Api(self))
+            Err(crate::Error::Api {
+                code: self.code,
+                message: self.message,
+            })
         }
     }
 
@@ -100,4 +143,32 @@ impl XiaoaiResponse {
     pub fn extract_data<T: DeserializeOwned>(self) -> crate::Result<T> {
         Ok(serde_json::from_value(self.data)?)
     }
+
+    /// 服务端为请求分配的 ID，找不到时返回 `None`。
+    ///
+    /// [`Xiaoai::nlp`]、[`Xiaoai::tts`] 等操作的响应中通常带有此 ID，可以用来在对话记录中找到对应的
+    /// [`Record`][conversation::Record::request_id]。会依次查找 `data` 和 UBUS 响应的 `info`
+    /// 中的 `request_id` 或 `requestId` 字段。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use miai::XiaoaiResponse;
+    /// # use serde_json::json;
+    /// let response = XiaoaiResponse {
+    ///     code: 0,
+    ///     message: "Success".into(),
+    ///     data: json!({"code": 0, "info": r#"{"request_id":"abc"}"#}),
+    /// };
+    /// assert_eq!(response.request_id().as_deref(), Some("abc"));
+    /// ```
+    pub fn request_id(&self) -> Option<String> {
+        let info = util::ubus_info(&self.data);
+        [&self.data, &info].into_iter().find_map(|value| {
+            ["request_id", "requestId"]
+                .into_iter()
+                .find_map(|key| value.get(key)?.as_str())
+                .map(str::to_string)
+        })
+    }
 }
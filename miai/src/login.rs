@@ -1,6 +1,10 @@
 //! 登录小爱服务。
 
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use base64ct::{Base64, Encoding};
 use cookie_store::{CookieStore, RawCookie};
@@ -10,14 +14,19 @@ use reqwest_cookie_store::CookieStoreMutex;
 use serde::{Deserialize, Serialize};
 use serde_json::{Number, Value};
 use sha1::Sha1;
+use time::OffsetDateTime;
 use tracing::trace;
 
-use crate::util::random_id;
+use crate::{
+    DEFAULT_APP_VERSION, redact,
+    util::{HttpOptions, RequestBuilderExt, random_id},
+};
 
 /// 登录小爱服务。
 ///
 /// 更低层级的抽象，可以用来辅助理解小爱服务的登录流程，或对登录进行更精细的控制。使用时需严格遵守先
 /// [`login`][Login::login]，再 [`auth`][Login::auth]，最后 [`get_token`][Login::get_token] 的步骤。
+/// 扫码登录的步骤参见 [`Login::new_qr`]。
 #[derive(Clone, Debug)]
 pub struct Login {
     client: Client,
@@ -25,13 +34,30 @@ pub struct Login {
     username: String,
     password_hash: String,
     cookie_store: Arc<CookieStoreMutex>,
+    timeout: Option<Duration>,
 }
 
 const LOGIN_SERVER: &str = "https://account.xiaomi.com/pass/";
-const LOGIN_UA: &str = "APP/com.xiaomi.mihome APPV/6.0.103 iosPassportSDK/3.9.0 iOS/14.4 miHSTS";
+
+/// 以米家 App 版本 `version` 登录时的 User-Agent。
+pub(crate) fn login_user_agent(version: &str) -> String {
+    format!("APP/com.xiaomi.mihome APPV/{version} iosPassportSDK/3.9.0 iOS/14.4 miHSTS")
+}
 
 impl Login {
     pub fn new(username: impl Into<String>, password: impl AsRef<[u8]>) -> crate::Result<Self> {
+        Self::with_credentials(username.into(), hash_password(password))
+    }
+
+    /// 创建用于扫码登录的 `Login`，无需账号密码。
+    ///
+    /// 使用时需遵守先 [`login`][Login::login]，再 [`qr_code`][Login::qr_code]，然后
+    /// [`wait_qr_code`][Login::wait_qr_code]，最后 [`get_token`][Login::get_token] 的步骤。
+    pub fn new_qr() -> crate::Result<Self> {
+        Self::with_credentials(String::new(), String::new())
+    }
+
+    fn with_credentials(username: String, password_hash: String) -> crate::Result<Self> {
         let server = Url::parse(LOGIN_SERVER)?;
 
         // 预先添加 Cookies
@@ -47,18 +73,73 @@ impl Login {
         // 用于登录的 Client
         let client = Client::builder()
             .cookie_provider(Arc::clone(&cookie_store))
-            .user_agent(LOGIN_UA)
+            .user_agent(login_user_agent(DEFAULT_APP_VERSION))
             .build()?;
 
         Ok(Self {
             client,
             server,
-            username: username.into(),
-            password_hash: hash_password(password),
+            username,
+            password_hash,
+            cookie_store,
+            timeout: None,
+        })
+    }
+
+    /// 从已有的 Cookies 恢复登录流程。
+    ///
+    /// Cookies 中需要包含此前登录留下的 `passToken`，此时无需账号密码，只需
+    /// [`refresh`][Login::refresh] 后再 [`get_token`][Login::get_token] 即可刷新登录状态。
+    pub fn from_cookie_store(cookie_store: Arc<CookieStoreMutex>) -> crate::Result<Self> {
+        let client = Client::builder()
+            .cookie_provider(Arc::clone(&cookie_store))
+            .user_agent(login_user_agent(DEFAULT_APP_VERSION))
+            .build()?;
+
+        Ok(Self {
+            client,
+            server: Url::parse(LOGIN_SERVER)?,
+            username: String::new(),
+            password_hash: String::new(),
             cookie_store,
+            timeout: None,
         })
     }
 
+    /// 使用 Cookies 中的 `passToken` 跳过认证，直接获得 [`Login::get_token`] 所需的数据。
+    ///
+    /// # Errors
+    ///
+    /// 当 `passToken` 失效，服务器要求重新认证时，返回 [`Error::SessionExpired`][crate::Error::SessionExpired]。
+    pub async fn refresh(&self) -> crate::Result<AuthResponse> {
+        let raw = self.raw_login().await?;
+        // 只有 `passToken` 有效时，初步登录才会直接成功
+        if raw.get("code").and_then(Value::as_i64) != Some(0) {
+            return Err(crate::Error::SessionExpired);
+        }
+
+        serde_json::from_value(raw).map_err(|_| crate::Error::SessionExpired)
+    }
+
+    /// 以新的网络选项重新构造用于登录的 `Client`，Cookies 保持不变。
+    pub(crate) fn with_http_options(mut self, options: &HttpOptions) -> crate::Result<Self> {
+        self.client = options
+            .apply(
+                Client::builder()
+                    .cookie_provider(Arc::clone(&self.cookie_store))
+                    .user_agent(options.login_user_agent()),
+            )?
+            .build()?;
+
+        Ok(self)
+    }
+
+    /// 为登录过程中的每个请求设置超时。
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
     /// 初步登录小爱服务。
     ///
     /// 结果中可能会出现登录失败的信息，但这无伤大雅，初步登录只是为了获取一些接下来认证所需的数据。
@@ -74,14 +155,14 @@ impl Login {
         let bytes = self
             .client
             .get(self.server.join("serviceLogin?sid=micoapi&_json=true")?)
-            .send()
+            .timeout_opt(self.timeout)
+            .send_logged()
             .await?
             .error_for_status()?
             .bytes()
             .await?;
-        // 前 11 个字节不知道是什么，后面追加 json 响应体
-        let response = serde_json::from_slice(&bytes[11..])?;
-        trace!("尝试初步登录: {response}");
+        let response = parse_json(&bytes)?;
+        trace!("尝试初步登录: {}", redact::json(&response));
 
         Ok(response)
     }
@@ -89,8 +170,23 @@ impl Login {
     /// 认证小爱服务。
     ///
     /// 需要使用初步登录的结果进行。
+    ///
+    /// # Errors
+    ///
+    /// 当账号或密码错误等导致认证失败时，返回 [`Error::Auth`][crate::Error::Auth]；
+    /// 当需要短信或邮箱验证时，返回 [`Error::VerificationRequired`][crate::Error::VerificationRequired]，
+    /// 此时应改用 [`Login::start_verification`] 和 [`Login::submit_code`] 完成认证。
     pub async fn auth(&self, login_response: LoginResponse) -> crate::Result<AuthResponse> {
         let raw = self.raw_auth(login_response).await?;
+        check_code(&raw)?;
+        // 触发风控时，认证成功但需要额外的验证
+        if let Some(url) = raw.get("notificationUrl").and_then(Value::as_str)
+            && !url.is_empty()
+        {
+            return Err(crate::Error::VerificationRequired(LoginChallenge {
+                notification_url: url.to_string(),
+            }));
+        }
 
         Ok(serde_json::from_value(raw)?)
     }
@@ -111,17 +207,202 @@ impl Login {
             .client
             .post(self.server.join("serviceLoginAuth2")?)
             .form(&form)
-            .send()
+            .timeout_opt(self.timeout)
+            .send_logged()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        let response = parse_json(&bytes)?;
+        trace!("尝试认证: {}", redact::json(&response));
+
+        Ok(response)
+    }
+
+    /// 开始额外的验证，服务端会向绑定的手机或邮箱发送验证码。
+    ///
+    /// 需要使用 [`Login::auth`] 返回的 [`LoginChallenge`] 进行，之后使用 [`Login::submit_code`] 提交验证码。
+    pub async fn start_verification(
+        &self,
+        challenge: &LoginChallenge,
+    ) -> crate::Result<Verification> {
+        // 查询可用的验证方式，同时获得验证所需的会话
+        let mut url = Url::parse(&challenge.notification_url)?;
+        let params: Vec<_> = url.query_pairs().into_owned().collect();
+        url.set_path("/identity/list");
+        let bytes = self
+            .client
+            .get(url)
+            .timeout_opt(self.timeout)
+            .send_logged()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        let response = parse_json(&bytes)?;
+        trace!("查询验证方式: {}", redact::json(&response));
+
+        let method = match response.get("flag").and_then(Value::as_u64) {
+            Some(4) => VerifyMethod::Phone,
+            Some(8) => VerifyMethod::Email,
+            _ => return Err(crate::Error::UnexpectedResponse(response)),
+        };
+        let verification = Verification { method, params };
+
+        // 发送验证码
+        let url = verification.url(
+            &self.server,
+            match method {
+                VerifyMethod::Phone => "/identity/auth/sendPhoneTicket",
+                VerifyMethod::Email => "/identity/auth/sendEmailTicket",
+            },
+        )?;
+        let form = HashMap::from([("retry", "0"), ("icode", ""), ("_json", "true")]);
+        let bytes = self
+            .client
+            .post(url)
+            .form(&form)
+            .timeout_opt(self.timeout)
+            .send_logged()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        let response = parse_json(&bytes)?;
+        trace!("发送验证码: {}", redact::json(&response));
+        check_code(&response)?;
+
+        Ok(verification)
+    }
+
+    /// 提交收到的验证码，完成认证并获得 [`Login::get_token`] 所需的数据。
+    ///
+    /// # Errors
+    ///
+    /// 当验证码错误时，返回 [`Error::Auth`][crate::Error::Auth]。
+    pub async fn submit_code(
+        &self,
+        verification: &Verification,
+        code: &str,
+    ) -> crate::Result<AuthResponse> {
+        let flag = verification.method.flag().to_string();
+        let url = verification.url(
+            &self.server,
+            match verification.method {
+                VerifyMethod::Phone => "/identity/auth/verifyPhone",
+                VerifyMethod::Email => "/identity/auth/verifyEmail",
+            },
+        )?;
+        let form = HashMap::from([
+            ("_flag", flag.as_str()),
+            ("ticket", code),
+            ("trust", "true"),
+            ("_json", "true"),
+        ]);
+        let bytes = self
+            .client
+            .post(url)
+            .form(&form)
+            .timeout_opt(self.timeout)
+            .send_logged()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        let response = parse_json(&bytes)?;
+        trace!("提交验证码: {}", redact::json(&response));
+        check_code(&response)?;
+
+        // 跟随跳转以获得 `passToken`，之后便可以直接登录
+        let location = response
+            .get("location")
+            .and_then(Value::as_str)
+            .ok_or_else(|| crate::Error::UnexpectedResponse(response.clone()))?;
+        self.client
+            .get(location)
+            .timeout_opt(self.timeout)
+            .send_logged()
+            .await?
+            .error_for_status()?;
+
+        self.refresh().await
+    }
+
+    /// 获取用于扫码登录的二维码。
+    ///
+    /// 需要使用初步登录的结果进行，二维码的内容需要用米家 App 扫描并确认。
+    pub async fn qr_code(&self, login_response: LoginResponse) -> crate::Result<QrCode> {
+        let raw = self.raw_qr_code(login_response).await?;
+
+        Ok(serde_json::from_value(raw)?)
+    }
+
+    /// 同 [`Login::qr_code`]，但返回原始的 JSON。
+    pub async fn raw_qr_code(&self, login_response: LoginResponse) -> crate::Result<Value> {
+        let dc = (OffsetDateTime::now_utc().unix_timestamp_nanos() / 1_000_000).to_string();
+        let url = Url::parse_with_params(
+            self.server.join("/longPolling/loginUrl")?.as_str(),
+            [
+                ("_json", "true"),
+                ("_qrsize", "240"),
+                ("qs", &login_response.qs),
+                ("sid", &login_response.sid),
+                ("_sign", &login_response._sign),
+                ("callback", &login_response.callback),
+                ("_local", "zh_CN"),
+                ("_dc", &dc),
+            ],
+        )?;
+        let bytes = self
+            .client
+            .get(url)
+            .timeout_opt(self.timeout)
+            .send_logged()
             .await?
             .error_for_status()?
             .bytes()
             .await?;
-        let response = serde_json::from_slice(&bytes[11..])?;
-        trace!("尝试认证: {response}");
+        let response = parse_json(&bytes)?;
+        trace!("获取登录二维码: {}", redact::json(&response));
 
         Ok(response)
     }
 
+    /// 等待用户扫描二维码并确认，获得 [`Login::get_token`] 所需的数据。
+    ///
+    /// 服务端会挂起请求直到用户确认，因此此函数可能会长时间等待，但不会超过二维码的有效期。
+    ///
+    /// # Errors
+    ///
+    /// 超过有效期仍未确认时，返回 [`Error::QrCodeExpired`][crate::Error::QrCodeExpired]；
+    /// 用户拒绝等导致认证失败时，返回 [`Error::Auth`][crate::Error::Auth]。
+    pub async fn wait_qr_code(&self, qr_code: &QrCode) -> crate::Result<AuthResponse> {
+        let deadline = Instant::now() + Duration::from_secs(qr_code.timeout);
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(crate::Error::QrCodeExpired);
+            }
+            // 长轮询，超时后再次发起
+            let response = self
+                .client
+                .get(&qr_code.poll_url)
+                .timeout(remaining)
+                .send_logged()
+                .await;
+            let bytes = match response {
+                Ok(response) => response.error_for_status()?.bytes().await?,
+                Err(err) if err.is_timeout() => continue,
+                Err(err) => return Err(err.into()),
+            };
+            let raw = parse_json(&bytes)?;
+            trace!("轮询扫码结果: {}", redact::json(&raw));
+            check_code(&raw)?;
+
+            return Ok(serde_json::from_value(raw)?);
+        }
+    }
+
     /// 获取小爱服务的 token，是登录的核心步骤。
     ///
     /// 需要在认证成功后进行。
@@ -132,16 +413,33 @@ impl Login {
         let response = self
             .client
             .get(url)
-            .send()
+            .timeout_opt(self.timeout)
+            .send_logged()
             .await?
             .error_for_status()?
             .json()
             .await?;
-        trace!("尝试获取 serviceToken: {response}");
+        trace!("尝试获取 serviceToken: {}", redact::json(&response));
 
         Ok(response)
     }
 
+    /// 注销登录，使服务器上的 `passToken` 失效。
+    ///
+    /// 注销后 Cookies 中的登录状态将无法再用于刷新。
+    pub async fn logout(&self) -> crate::Result<()> {
+        let response = self
+            .client
+            .get(self.server.join("logout")?)
+            .timeout_opt(self.timeout)
+            .send_logged()
+            .await?
+            .error_for_status()?;
+        trace!("注销登录: {}", response.status());
+
+        Ok(())
+    }
+
     /// 消耗 `Login` 并提取 Cookies，其中存储了当前的登录状态。
     pub fn into_cookie_store(self) -> Arc<CookieStoreMutex> {
         self.cookie_store
@@ -157,6 +455,68 @@ pub struct LoginResponse {
     pub callback: String,
 }
 
+/// 密码登录时，服务端要求的额外验证，参见 [`Login::start_verification`]。
+#[derive(Clone, Debug)]
+pub struct LoginChallenge {
+    /// 服务端给出的验证页面链接，其中带有后续验证所需的参数。
+    pub notification_url: String,
+}
+
+/// 接收验证码的方式。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerifyMethod {
+    /// 短信验证码。
+    Phone,
+    /// 邮箱验证码。
+    Email,
+}
+
+impl VerifyMethod {
+    fn flag(self) -> u8 {
+        match self {
+            Self::Phone => 4,
+            Self::Email => 8,
+        }
+    }
+}
+
+/// 已发送验证码的验证流程，使用 [`Login::submit_code`] 完成。
+#[derive(Clone, Debug)]
+pub struct Verification {
+    /// 验证码的发送方式。
+    pub method: VerifyMethod,
+    /// 验证页面链接中的参数，需要原样带回。
+    params: Vec<(String, String)>,
+}
+
+impl Verification {
+    fn url(&self, server: &Url, path: &str) -> crate::Result<Url> {
+        let dc = (OffsetDateTime::now_utc().unix_timestamp_nanos() / 1_000_000).to_string();
+        let mut url = server.join(path)?;
+        url.query_pairs_mut()
+            .extend_pairs(&self.params)
+            .append_pair("_dc", &dc);
+
+        Ok(url)
+    }
+}
+
+/// [`Login::qr_code`] 的响应体。
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QrCode {
+    /// 二维码的内容，需要用米家 App 扫描。
+    pub login_url: String,
+    /// 二维码图片的链接。
+    #[serde(rename = "qr")]
+    pub image_url: String,
+    /// 用于轮询扫码结果的链接。
+    #[serde(rename = "lp")]
+    pub poll_url: String,
+    /// 二维码的有效期，单位为秒。
+    pub timeout: u64,
+}
+
 /// [`Login::auth`] 的响应体，但仅包含 [`Login::get_token`] 所需的字段。
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct AuthResponse {
@@ -165,6 +525,36 @@ pub struct AuthResponse {
     pub ssecurity: String,
 }
 
+/// 解析登录服务的响应体。
+///
+/// 响应体前通常带有 `&&&START&&&`，之后才是 json。
+fn parse_json(bytes: &[u8]) -> crate::Result<Value> {
+    let bytes = bytes.strip_prefix(b"&&&START&&&").unwrap_or(bytes);
+
+    Ok(serde_json::from_slice(bytes)?)
+}
+
+/// 校验登录服务响应体的 `code`，非 0 时返回 [`Error::Auth`][crate::Error::Auth]。
+fn check_code(response: &Value) -> crate::Result<()> {
+    let code = response
+        .get("code")
+        .and_then(Value::as_i64)
+        .unwrap_or_default();
+    if code == 0 {
+        return Ok(());
+    }
+    let message = response
+        .get("desc")
+        .or_else(|| response.get("tips"))
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+
+    Err(crate::Error::Auth {
+        code,
+        message: message.to_string(),
+    })
+}
+
 fn random_device_id() -> String {
     let mut device_id = random_id(16);
     device_id.make_ascii_uppercase();
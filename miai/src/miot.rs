@@ -0,0 +1,61 @@
+//! 小爱设备的 MIoT 属性。
+//!
+//! MIoT 规范用服务 ID（`siid`）和属性 ID（`piid`）描述设备的每个属性，比如音量、屏幕亮度或童锁。
+//! 不同机型的 ID 并不相同，可以在 [MIoT 规范](https://home.miot-spec.com/) 中按型号查询，
+//! 型号通常为 `xiaomi.wifispeaker.` 加上小写的 [`hardware`][crate::DeviceInfo::hardware]，比如
+//! `xiaomi.wifispeaker.lx06`。
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// 读取或设置 MIoT 属性的结果。
+#[non_exhaustive]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Property {
+    /// 服务 ID。
+    pub siid: u32,
+
+    /// 属性 ID。
+    pub piid: u32,
+
+    /// MIoT 状态码，0 表示成功，负数表示失败。
+    ///
+    /// 服务端没有返回该属性的结果时为 -1。
+    #[serde(default)]
+    pub code: i64,
+
+    /// 属性的值。
+    ///
+    /// 读取成功时才有值，设置属性时通常为空。
+    #[serde(default)]
+    pub value: Option<Value>,
+}
+
+impl Property {
+    /// 是否成功，即 [`code`][Self::code] 为 0。
+    pub fn is_ok(&self) -> bool {
+        self.code == 0
+    }
+
+    /// 服务端没有返回结果的属性。
+    pub(crate) fn missing(siid: u32, piid: u32) -> Self {
+        Self {
+            siid,
+            piid,
+            code: -1,
+            value: None,
+        }
+    }
+}
+
+/// 从 UBUS 响应的 `info` 中解析属性的结果。
+///
+/// 结果可能直接作为 `info`，也可能包在 `result` 里。
+pub(crate) fn parse_properties(info: Value) -> crate::Result<Vec<Property>> {
+    let results = match info {
+        Value::Object(mut info) => info.remove("result").unwrap_or_default(),
+        info => info,
+    };
+
+    Ok(serde_json::from_value(results)?)
+}
@@ -0,0 +1,72 @@
+/// 已知的设备机型，用于把 [`DeviceInfo::hardware`][crate::DeviceInfo::hardware] 这样的机型代码对应到可读的名称。
+///
+/// 机型列表整理自社区的观察，并不完整，找不到的机型代码请直接使用
+/// [`DeviceInfo::hardware`][crate::DeviceInfo::hardware]。
+///
+/// # Examples
+///
+/// ```
+/// # use miai::DeviceModel;
+/// let model = DeviceModel::lookup("lx06").unwrap();
+/// assert_eq!(model.name, "小爱音箱 Pro");
+/// assert!(!model.screen);
+/// assert!(DeviceModel::lookup("UNKNOWN").is_none());
+/// ```
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DeviceModel {
+    /// 机型代码，如 `LX06`。
+    pub hardware: &'static str,
+    /// 可读的名称。
+    pub name: &'static str,
+    /// 是否带有屏幕。
+    pub screen: bool,
+    /// 设备内部音量的最大值。
+    ///
+    /// 目前已知的机型，包括带屏幕的机型，都直接使用 0 到 [`MAX_VOLUME`][crate::MAX_VOLUME]，
+    /// 因此设置和读取音量时不做换算。参见 [`Xiaoai::max_volume`][crate::Xiaoai::max_volume]。
+    pub volume_scale: u32,
+}
+
+impl DeviceModel {
+    /// 所有已知的机型。
+    pub const ALL: &'static [Self] = &[
+        Self::new("LX01", "小爱音箱 mini", false),
+        Self::new("LX04", "小爱触屏音箱", true),
+        Self::new("LX05", "小爱音箱 Play（2019 款）", false),
+        Self::new("LX05A", "小爱音箱 Play 增强版", false),
+        Self::new("LX06", "小爱音箱 Pro", false),
+        Self::new("L04M", "小爱音箱万能遥控版", false),
+        Self::new("L05B", "小爱音箱 Play", false),
+        Self::new("L05C", "小爱音箱 Play 增强版", false),
+        Self::new("L06A", "小爱音箱", false),
+        Self::new("L07A", "Redmi 小爱音箱 Play", false),
+        Self::new("L09A", "小米音箱 Art", false),
+        Self::new("L09B", "小米音箱 Art 电池版", false),
+        Self::new("L15A", "Xiaomi 智能音箱", false),
+        Self::new("L16A", "Xiaomi Sound", false),
+        Self::new("L17A", "Xiaomi Sound Pro", false),
+        Self::new("S12", "小米 AI 音箱", false),
+        Self::new("S12A", "小米 AI 音箱", false),
+        Self::new("X08A", "Redmi 小爱触屏音箱 8", true),
+        Self::new("X08C", "Redmi 小爱触屏音箱", true),
+        Self::new("X08E", "Redmi 小爱触屏音箱 Pro 8", true),
+        Self::new("X10A", "小米智能家庭屏 10", true),
+    ];
+
+    const fn new(hardware: &'static str, name: &'static str, screen: bool) -> Self {
+        Self {
+            hardware,
+            name,
+            screen,
+            volume_scale: crate::MAX_VOLUME,
+        }
+    }
+
+    /// 根据机型代码查找机型，不区分大小写，未知的机型返回 `None`。
+    pub fn lookup(hardware: &str) -> Option<&'static Self> {
+        Self::ALL
+            .iter()
+            .find(|model| model.hardware.eq_ignore_ascii_case(hardware.trim()))
+    }
+}
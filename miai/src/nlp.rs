@@ -0,0 +1,145 @@
+use std::collections::VecDeque;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::{XiaoaiResponse, util::ubus_info};
+
+/// 对响应嵌套的层数有限的搜索，避免在巨大的响应中浪费时间。
+const MAX_DEPTH: usize = 5;
+
+/// [`Xiaoai::nlp`][crate::Xiaoai::nlp] 的结构化结果，即小爱识别出的意图、槽位和答复。
+///
+/// 响应的结构因机型和技能而异，这里会在响应中按常见的字段名查找，找不到的字段为空。
+/// 完全无法识别时各字段都为空，可以通过 [`Self::raw`] 自行解析。
+///
+/// # Examples
+///
+/// ```
+/// # use miai::{NlpResult, XiaoaiResponse};
+/// # use serde_json::json;
+/// let info = json!({
+///     "response": {
+///         "intention": {"domain": "weather", "intent": "query", "confidence": 0.9},
+///         "slots": [{"name": "city", "value": "北京"}],
+///         "to_speak": "北京今天晴"
+///     }
+/// });
+/// let response = XiaoaiResponse {
+///     code: 0,
+///     message: "Success".into(),
+///     data: json!({"code": 0, "info": info.to_string()}),
+/// };
+///
+/// let result = NlpResult::from(response);
+/// assert_eq!(result.intent.as_deref(), Some("query"));
+/// assert_eq!(result.confidence, Some(0.9));
+/// assert_eq!(result.slots["city"], "北京");
+/// assert_eq!(result.answer.as_deref(), Some("北京今天晴"));
+///
+/// let unknown = NlpResult::from(XiaoaiResponse {
+///     code: 0,
+///     message: "Success".into(),
+///     data: json!({"code": 0, "info": "{}"}),
+/// });
+/// assert!(!unknown.is_recognized());
+/// ```
+#[non_exhaustive]
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NlpResult {
+    /// 意图的名称。
+    pub intent: Option<String>,
+
+    /// 意图的置信度，通常在 0 到 1 之间。
+    pub confidence: Option<f64>,
+
+    /// 槽位，以槽位名称为键。
+    pub slots: Map<String, Value>,
+
+    /// 答复的文本。
+    pub answer: Option<String>,
+
+    /// 原始的数据，即 UBUS 调用的 `info`，没有时为响应的 `data`。
+    pub raw: Value,
+}
+
+impl NlpResult {
+    /// 是否识别出了意图或答复。
+    pub fn is_recognized(&self) -> bool {
+        self.intent.is_some() || self.answer.is_some()
+    }
+}
+
+impl From<XiaoaiResponse> for NlpResult {
+    fn from(response: XiaoaiResponse) -> Self {
+        let raw = match ubus_info(&response.data) {
+            Value::Null => response.data,
+            info => info,
+        };
+        let non_empty = |value: &Value| {
+            value
+                .as_str()
+                .map(str::trim)
+                .filter(|text| !text.is_empty())
+                .map(str::to_string)
+        };
+
+        let intent = find_map(&raw, &["intent", "intention"], |value| match value {
+            Value::Object(intent) => ["intent", "name"]
+                .into_iter()
+                .find_map(|key| non_empty(intent.get(key)?)),
+            value => non_empty(value),
+        });
+        let confidence = find_map(&raw, &["confidence", "score"], Value::as_f64);
+        let slots = find_map(&raw, &["slots"], slots).unwrap_or_default();
+        let answer = find_map(&raw, &["to_speak", "toSpeak", "text"], non_empty);
+
+        Self {
+            intent,
+            confidence,
+            slots,
+            answer,
+            raw,
+        }
+    }
+}
+
+/// 槽位可能是对象，也可能是 `{"name": ..., "value": ...}` 的数组。
+fn slots(value: &Value) -> Option<Map<String, Value>> {
+    match value {
+        Value::Object(slots) => Some(slots.clone()),
+        Value::Array(slots) => Some(
+            slots
+                .iter()
+                .filter_map(|slot| {
+                    let name = slot.get("name")?.as_str()?;
+                    Some((name.to_string(), slot.get("value")?.clone()))
+                })
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// 广度优先地查找 `keys` 中的字段，返回第一个能被 `f` 接受的结果，越浅的字段越优先。
+fn find_map<T>(value: &Value, keys: &[&str], f: impl Fn(&Value) -> Option<T>) -> Option<T> {
+    let mut queue = VecDeque::from([(value, 0)]);
+    while let Some((value, depth)) = queue.pop_front() {
+        let children: Box<dyn Iterator<Item = &Value>> = match value {
+            Value::Object(object) => {
+                if let Some(found) = keys.iter().filter_map(|key| object.get(*key)).find_map(&f) {
+                    return Some(found);
+                }
+                Box::new(object.values())
+            }
+            Value::Array(array) => Box::new(array.iter()),
+            _ => continue,
+        };
+        if depth < MAX_DEPTH {
+            queue.extend(children.map(|child| (child, depth + 1)));
+        }
+    }
+
+    None
+}
@@ -0,0 +1,255 @@
+use std::{fmt, str::FromStr, time::Duration};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::util::ubus_info;
+
+/// 设置播放器播放状态的操作，参见 [`Xiaoai::set_play_state`][crate::Xiaoai::set_play_state]。
+///
+/// 查询到的播放状态参见 [`PlaybackState`]。
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PlayState {
+    Play,
+    Pause,
+    Stop,
+    /// 在播放和暂停之间切换。
+    Toggle,
+    /// 下一首。
+    Next,
+    /// 上一首。
+    Previous,
+}
+
+/// 查询到的播放器状态，参见 [`PlayerStatus::state`]。
+///
+/// 解析自 `player_get_play_status` 的 `status` 字段，与设置用的 [`PlayState`] 相互独立。
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PlaybackState {
+    /// 空闲或已停止，状态码 0。
+    Stopped,
+    /// 正在播放，状态码 1。
+    Playing,
+    /// 已暂停，状态码 2。
+    Paused,
+    /// 正在加载或缓冲，状态码 3，常见于刚开始播放链接时。
+    Buffering,
+    /// 未知的状态码。
+    Unknown(u64),
+}
+
+impl PlaybackState {
+    fn from_status(status: u64) -> Self {
+        match status {
+            0 => Self::Stopped,
+            1 => Self::Playing,
+            2 => Self::Paused,
+            3 => Self::Buffering,
+            status => Self::Unknown(status),
+        }
+    }
+
+    /// 是否正在播放或即将播放，即 [`Playing`][Self::Playing] 或 [`Buffering`][Self::Buffering]。
+    pub fn is_active(self) -> bool {
+        matches!(self, Self::Playing | Self::Buffering)
+    }
+}
+
+/// 播放器的循环播放模式。
+///
+/// 可以和字符串相互转换，分别为 `sequence`、`repeat-all`、`repeat-one` 和 `shuffle`。
+///
+/// # Examples
+///
+/// ```
+/// # use miai::PlayMode;
+/// let mode: PlayMode = "repeat-one".parse().unwrap();
+/// assert_eq!(mode, PlayMode::RepeatOne);
+/// assert_eq!(mode.to_string(), "repeat-one");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PlayMode {
+    /// 顺序播放，播放完列表后停止。
+    Sequence,
+    /// 列表循环。
+    RepeatAll,
+    /// 单曲循环。
+    RepeatOne,
+    /// 随机播放，部分机型不支持。
+    Shuffle,
+}
+
+impl PlayMode {
+    /// 所有的循环播放模式。
+    pub const ALL: [Self; 4] = [
+        Self::Sequence,
+        Self::RepeatAll,
+        Self::RepeatOne,
+        Self::Shuffle,
+    ];
+
+    /// 对应 mina 接口中的 `loop_type`。
+    ///
+    /// 这些值来自于对官方 App 的观察，并没有文档。
+    pub(crate) fn loop_type(self) -> u64 {
+        match self {
+            Self::RepeatOne => 0,
+            Self::RepeatAll => 1,
+            Self::Shuffle => 3,
+            Self::Sequence => 4,
+        }
+    }
+
+    pub(crate) fn from_loop_type(loop_type: u64) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.loop_type() == loop_type)
+    }
+}
+
+impl fmt::Display for PlayMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Sequence => "sequence",
+            Self::RepeatAll => "repeat-all",
+            Self::RepeatOne => "repeat-one",
+            Self::Shuffle => "shuffle",
+        })
+    }
+}
+
+impl FromStr for PlayMode {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.to_string() == s.trim())
+            .ok_or_else(|| crate::Error::InvalidInput(format!("无效的播放模式 `{s}`")))
+    }
+}
+
+/// 播放器的状态信息。
+#[non_exhaustive]
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerStatus {
+    /// 播放状态。
+    pub state: PlaybackState,
+
+    /// 音量，范围为 0 到 100。
+    pub volume: u8,
+
+    /// 正在播放的标题。
+    pub title: Option<String>,
+
+    /// 正在播放的歌手。
+    pub artist: Option<String>,
+
+    /// 正在播放的专辑。
+    pub album: Option<String>,
+
+    /// 正在播放的封面图片链接。
+    ///
+    /// 不同机型的字段名不同，如 `cover`、`coverUrl` 或 `cover_url`，都会尝试。
+    pub cover_url: Option<String>,
+
+    /// 媒体的总时长。
+    ///
+    /// 序列化为毫秒数。
+    #[serde(with = "millis")]
+    pub duration: Option<Duration>,
+
+    /// 当前的播放进度。
+    ///
+    /// 序列化为毫秒数。
+    #[serde(with = "millis")]
+    pub position: Option<Duration>,
+
+    /// 循环播放模式。
+    pub play_mode: Option<PlayMode>,
+}
+
+impl PlayerStatus {
+    /// 播放进度的比例，范围为 0 到 1。
+    ///
+    /// 缺少进度或时长，或者时长为 0 时返回 0。
+    pub fn progress(&self) -> f32 {
+        match (self.position, self.duration) {
+            (Some(position), Some(duration)) if !duration.is_zero() => {
+                (position.as_secs_f32() / duration.as_secs_f32()).clamp(0.0, 1.0)
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// 从 `player_get_play_status` 响应的 `data` 中解析。
+    ///
+    /// 有的机型把状态放在 `info` 里，有的则直接放在 `data` 里，两者都会尝试。
+    pub(crate) fn from_data(data: &Value) -> Option<Self> {
+        let info = ubus_info(data);
+        let field = |key| [&info, data].into_iter().find_map(|value| value.get(key));
+        let state = PlaybackState::from_status(as_u64(field("status")?)?);
+        let volume = as_u64(field("volume")?)?.min(100) as u8;
+
+        let detail = field("play_song_detail");
+        let detail_field = |key| detail.and_then(|detail| detail.get(key));
+        // 依次尝试各机型使用的字段名
+        let string = |keys: &[&'static str]| {
+            keys.iter().find_map(|&key| {
+                detail_field(key)
+                    .and_then(Value::as_str)
+                    .filter(|s| !s.is_empty())
+                    .map(str::to_owned)
+            })
+        };
+
+        Some(Self {
+            state,
+            volume,
+            title: string(&["title"]),
+            artist: string(&["artist"]),
+            album: string(&["album", "album_name", "albumName"]),
+            cover_url: string(&["cover", "coverUrl", "cover_url", "cover_img"]),
+            duration: detail_field("duration")
+                .and_then(as_u64)
+                .map(Duration::from_millis),
+            position: detail_field("position")
+                .and_then(as_u64)
+                .map(Duration::from_millis),
+            play_mode: field("loop_type")
+                .and_then(as_u64)
+                .and_then(PlayMode::from_loop_type),
+        })
+    }
+}
+
+/// 数字可能以字符串的形式出现。
+fn as_u64(value: &Value) -> Option<u64> {
+    match value {
+        Value::Number(number) => number.as_u64(),
+        Value::String(string) => string.parse().ok(),
+        _ => None,
+    }
+}
+
+/// 以毫秒数序列化时长。
+mod millis {
+    use std::time::Duration;
+
+    use serde::Serializer;
+
+    pub fn serialize<S: Serializer>(
+        duration: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match duration {
+            Some(duration) => serializer.serialize_u128(duration.as_millis()),
+            None => serializer.serialize_none(),
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use tracing::debug;
+
+/// 客户端的请求限速，每 `per` 最多 `burst` 个请求。
+///
+/// 请求按到达顺序预约时间，超出时排队等待。克隆后共享同一个限速状态。
+#[derive(Clone, Debug)]
+pub(crate) struct RateLimiter {
+    per: Duration,
+    interval: Duration,
+    /// 下一个请求理论上的时间，参见 GCRA。
+    next: Arc<Mutex<Option<Instant>>>,
+}
+
+impl RateLimiter {
+    pub fn new(per: Duration, burst: usize) -> Self {
+        let burst = u32::try_from(burst.max(1)).unwrap_or(u32::MAX);
+
+        Self {
+            per,
+            interval: per / burst,
+            next: Arc::default(),
+        }
+    }
+
+    /// 等待直到可以发出请求。
+    ///
+    /// # Panics
+    ///
+    /// 当内部发生锁中毒时会 panic。
+    pub async fn acquire(&self) {
+        let wait = {
+            let mut next = self.next.lock().unwrap();
+            let now = Instant::now();
+            let theoretical = next.map_or(now, |next| next.max(now));
+            *next = Some(theoretical + self.interval);
+            (theoretical + self.interval).saturating_duration_since(now + self.per)
+        };
+        if !wait.is_zero() {
+            debug!("请求过于频繁，等待 {wait:?}");
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
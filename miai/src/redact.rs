@@ -0,0 +1,89 @@
+//! 日志中的脱敏处理，避免令牌、密码和 Cookies 出现在日志里。
+
+use std::fmt;
+
+use reqwest::{
+    Url,
+    header::{AUTHORIZATION, COOKIE, HeaderMap, SET_COOKIE},
+};
+use serde_json::Value;
+
+const REDACTED: &str = "***";
+
+/// 名称中包含这些片段的字段视为敏感，不区分大小写。
+const SENSITIVE: [&str; 9] = [
+    "token",
+    "password",
+    "hash",
+    "ssecurity",
+    "nonce",
+    "sign",
+    "cookie",
+    "ticket",
+    "secret",
+];
+
+fn is_sensitive(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    SENSITIVE.iter().any(|part| name.contains(part))
+}
+
+/// 隐藏查询参数中的敏感值。
+pub fn url(url: &Url) -> Url {
+    if url.query().is_none() {
+        return url.clone();
+    }
+    let pairs: Vec<_> = url
+        .query_pairs()
+        .map(|(name, value)| {
+            let value = if is_sensitive(&name) {
+                REDACTED.into()
+            } else {
+                value
+            };
+            (name.into_owned(), value.into_owned())
+        })
+        .collect();
+    let mut url = url.clone();
+    url.query_pairs_mut().clear().extend_pairs(pairs);
+
+    url
+}
+
+/// 隐藏 Cookies 和认证相关的请求头。
+pub fn headers(headers: &HeaderMap) -> impl fmt::Debug {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if [COOKIE, SET_COOKIE, AUTHORIZATION].contains(name) {
+                REDACTED
+            } else {
+                value.to_str().unwrap_or(REDACTED)
+            };
+            (name.as_str(), value)
+        })
+        .collect::<Vec<_>>()
+}
+
+/// 递归地隐藏 JSON 中的敏感字段，链接中的敏感参数同样会被隐藏。
+pub fn json(value: &Value) -> Value {
+    match value {
+        Value::Object(object) => object
+            .iter()
+            .map(|(key, value)| {
+                let value = if is_sensitive(key) {
+                    Value::from(REDACTED)
+                } else {
+                    json(value)
+                };
+                (key.clone(), value)
+            })
+            .collect(),
+        Value::Array(array) => array.iter().map(json).collect(),
+        Value::String(string) => match Url::parse(string) {
+            Ok(parsed) if parsed.query().is_some() => Value::from(url(&parsed).as_str()),
+            _ => value.clone(),
+        },
+        value => value.clone(),
+    }
+}
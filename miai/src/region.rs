@@ -0,0 +1,93 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// 小米账号所在的地区。
+///
+/// 小米账号的登录服务是全球共用的，但小爱相关的服务（`api2.mina.mi.com` 和 `userprofile.mina.mi.com`）
+/// 只部署在中国大陆，因此目前只有 [`Region::Cn`] 可用。选择其他地区时，
+/// [`XiaoaiBuilder`][crate::XiaoaiBuilder] 会在发出任何请求前返回
+/// [`Error::UnsupportedRegion`][crate::Error::UnsupportedRegion]，而不是等到认证失败。
+///
+/// # Examples
+///
+/// ```
+/// use miai::Region;
+///
+/// let region: Region = "de".parse().unwrap();
+/// assert_eq!(region, Region::De);
+/// assert!(!region.is_supported());
+/// assert!(Region::default().is_supported());
+/// ```
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Region {
+    /// 中国大陆。
+    #[default]
+    Cn,
+
+    /// 欧洲。
+    De,
+
+    /// 美国。
+    Us,
+
+    /// 新加坡。
+    Sg,
+
+    /// 俄罗斯。
+    Ru,
+
+    /// 印度。
+    I2,
+}
+
+impl Region {
+    /// 所有的地区。
+    pub const ALL: [Region; 6] = [
+        Region::Cn,
+        Region::De,
+        Region::Us,
+        Region::Sg,
+        Region::Ru,
+        Region::I2,
+    ];
+
+    /// 该地区是否提供小爱服务。
+    pub fn is_supported(self) -> bool {
+        self.api_server().is_some()
+    }
+
+    /// 小爱服务的地址，没有小爱服务的地区为 `None`。
+    pub(crate) fn api_server(self) -> Option<&'static str> {
+        match self {
+            Self::Cn => Some("https://api2.mina.mi.com/"),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Cn => "cn",
+            Self::De => "de",
+            Self::Us => "us",
+            Self::Sg => "sg",
+            Self::Ru => "ru",
+            Self::I2 => "i2",
+        })
+    }
+}
+
+impl FromStr for Region {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|region| region.to_string().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| crate::Error::InvalidInput(format!("无效的地区 `{s}`")))
+    }
+}
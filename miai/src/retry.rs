@@ -0,0 +1,38 @@
+use std::time::Duration;
+
+use rand::{Rng, rng};
+
+/// 重试策略。
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct RetryPolicy {
+    /// 最多尝试的次数，包括第一次请求。
+    pub max_attempts: u32,
+    /// 第一次重试前等待的时间，之后每次翻倍。
+    pub base_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// 不重试。
+    pub const NEVER: Self = Self {
+        max_attempts: 1,
+        base_delay: Duration::ZERO,
+    };
+
+    /// 第 `attempt` 次请求失败后，重试前需要等待的时间。
+    ///
+    /// 在指数退避的基础上加入随机抖动，取值范围为退避时间的一半到全部。
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)));
+        let jitter = rng().random_range(0.5..=1.0);
+
+        backoff.mul_f64(jitter)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::NEVER
+    }
+}
@@ -0,0 +1,188 @@
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use reqwest::Url;
+use tokio::{
+    fs::File,
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+use tracing::{debug, trace};
+
+use crate::util::random_id;
+
+/// 请求头的最大长度，超过则直接断开。
+const MAX_HEAD_LEN: usize = 8 * 1024;
+
+/// 在局域网内临时提供单个文件的 HTTP 服务。
+///
+/// 只响应 `GET` 和 `HEAD`，支持 `Range: bytes=start-` 形式的断点请求。被 drop 时停止服务。
+pub struct FileServer {
+    url: Url,
+    handle: JoinHandle<()>,
+}
+
+impl FileServer {
+    /// 开始提供 `path` 的服务。
+    ///
+    /// 监听所有网卡的随机端口，并选择通往外网的那张网卡的地址作为链接的主机。
+    /// 链接中带有随机的路径，避免被随意访问。
+    pub async fn serve(path: impl AsRef<Path>) -> crate::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        // 提前检查文件是否可读
+        File::open(&path).await?;
+
+        let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+        let addr = SocketAddr::new(lan_ip()?, listener.local_addr()?.port());
+        let token = random_id(16);
+        let mut url = Url::parse(&format!("http://{addr}/"))?;
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        url.path_segments_mut()
+            .expect("http 链接总是有路径的")
+            .extend([token.as_str(), &file_name]);
+        debug!("开始提供文件服务: {url}");
+
+        let state = Arc::new(State {
+            path,
+            request_path: url.path().to_string(),
+        });
+        let handle = tokio::spawn(async move {
+            while let Ok((stream, peer)) = listener.accept().await {
+                let state = Arc::clone(&state);
+                tokio::spawn(async move {
+                    if let Err(err) = state.handle(stream).await {
+                        trace!("处理来自 {peer} 的请求失败: {err}");
+                    }
+                });
+            }
+        });
+
+        Ok(Self { url, handle })
+    }
+
+    /// 文件的链接。
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+}
+
+impl Drop for FileServer {
+    fn drop(&mut self) {
+        debug!("停止文件服务: {}", self.url);
+        self.handle.abort();
+    }
+}
+
+struct State {
+    path: PathBuf,
+    request_path: String,
+}
+
+impl State {
+    async fn handle(&self, stream: TcpStream) -> io::Result<()> {
+        let mut stream = BufReader::new(stream);
+        let mut head = String::new();
+        // 逐行读取请求头，直到空行
+        loop {
+            let len = stream.read_line(&mut head).await?;
+            if len == 0 || head.ends_with("\r\n\r\n") || head.ends_with("\n\n") {
+                break;
+            }
+            if head.len() > MAX_HEAD_LEN {
+                return Ok(());
+            }
+        }
+        trace!("收到请求: {head:?}");
+
+        let mut lines = head.lines();
+        let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+        let method = request_line.next().unwrap_or_default();
+        let path = request_line.next().unwrap_or_default();
+        let range_start = lines
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("range"))
+            .and_then(|(_, value)| value.trim().strip_prefix("bytes="))
+            .and_then(|range| range.split('-').next())
+            .and_then(|start| start.parse::<u64>().ok());
+
+        let stream = stream.get_mut();
+        if !matches!(method, "GET" | "HEAD") {
+            return respond_empty(stream, "405 Method Not Allowed").await;
+        }
+        if path != self.request_path {
+            return respond_empty(stream, "404 Not Found").await;
+        }
+
+        let mut file = File::open(&self.path).await?;
+        let len = file.metadata().await?.len();
+        let start = range_start.unwrap_or(0);
+        if start > len {
+            return respond_empty(stream, "416 Range Not Satisfiable").await;
+        }
+        let status = if range_start.is_some() {
+            "206 Partial Content"
+        } else {
+            "200 OK"
+        };
+        let mut response = format!(
+            "HTTP/1.1 {status}\r\nContent-Type: {}\r\nContent-Length: {}\r\nAccept-Ranges: bytes\r\nConnection: close\r\n",
+            content_type(&self.path),
+            len - start,
+        );
+        if range_start.is_some() {
+            response += &format!(
+                "Content-Range: bytes {start}-{}/{len}\r\n",
+                len.saturating_sub(1)
+            );
+        }
+        response += "\r\n";
+        stream.write_all(response.as_bytes()).await?;
+
+        if method == "GET" {
+            file.seek(io::SeekFrom::Start(start)).await?;
+            tokio::io::copy(&mut file.take(len - start), stream).await?;
+        }
+
+        stream.shutdown().await
+    }
+}
+
+async fn respond_empty(stream: &mut TcpStream, status: &str) -> io::Result<()> {
+    let response = format!("HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+    stream.write_all(response.as_bytes()).await?;
+
+    stream.shutdown().await
+}
+
+/// 本机在局域网中的地址。
+///
+/// 通过“连接”一个外网地址让系统选择网卡，UDP 的连接并不会真正发出数据包。
+fn lan_ip() -> io::Result<IpAddr> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    socket.connect((Ipv4Addr::new(223, 5, 5, 5), 80))?;
+
+    Ok(socket.local_addr()?.ip())
+}
+
+fn content_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("mp3") => "audio/mpeg",
+        Some("wav") => "audio/wav",
+        Some("m4a" | "mp4") => "audio/mp4",
+        Some("aac") => "audio/aac",
+        Some("flac") => "audio/flac",
+        Some("ogg" | "oga") => "audio/ogg",
+        _ => "application/octet-stream",
+    }
+}
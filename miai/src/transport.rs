@@ -0,0 +1,213 @@
+use std::fmt;
+
+use futures_util::future::BoxFuture;
+use reqwest::{Client, Request, Response};
+
+use crate::util::execute_logged;
+
+/// 发送小爱服务请求的 HTTP 层，可以通过 [`XiaoaiBuilder::transport`][crate::XiaoaiBuilder::transport] 替换。
+///
+/// 默认使用 [`reqwest::Client`]。替换后，[`Xiaoai`][crate::Xiaoai] 的请求都会交给它发送，
+/// 请求中已经带有登录状态的 Cookies，响应中的 `Set-Cookie` 也会被保存，主要用于在测试中模拟服务端，
+/// 参见 `MockTransport`（需要启用 `test-util` 特性）。
+///
+/// 登录、刷新和注销登录的请求发往登录服务，不经过 `Transport`。
+pub trait Transport: fmt::Debug + Send + Sync + 'static {
+    /// 发送请求并返回响应。
+    ///
+    /// 只有无法得到响应时才应该返回错误，HTTP 状态码由调用者检查。
+    fn execute(&self, request: Request) -> BoxFuture<'_, crate::Result<Response>>;
+}
+
+impl Transport for Client {
+    fn execute(&self, request: Request) -> BoxFuture<'_, crate::Result<Response>> {
+        Box::pin(async move { Ok(execute_logged(self, request).await?) })
+    }
+}
+
+#[cfg(feature = "test-util")]
+pub use mock::*;
+
+#[cfg(feature = "test-util")]
+mod mock {
+    use std::{
+        collections::{HashMap, VecDeque},
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use futures_util::future::{self, BoxFuture};
+    use reqwest::{Method, Request, Response, StatusCode, Url};
+    use serde_json::{Value, json};
+
+    use super::Transport;
+
+    /// 模拟小爱服务的 [`Transport`]，返回预先设置的响应，并记录收到的请求。
+    ///
+    /// 按请求方法和路径匹配响应，路径同 [`Xiaoai::get`][crate::Xiaoai::get] 的 `uri`，不包括查询参数。
+    /// 同一个请求设置了多个响应时依次返回，最后一个会一直重复。没有设置响应的请求返回 404。
+    ///
+    /// `MockTransport` 的克隆共享同一份状态，可以在交给 [`Xiaoai`][crate::Xiaoai] 后继续设置响应和检查请求。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use miai::{MockTransport, Xiaoai};
+    /// # use reqwest::Method;
+    /// # use serde_json::json;
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> miai::Result<()> {
+    /// let mock = MockTransport::new();
+    /// mock.respond(
+    ///     Method::GET,
+    ///     "admin/v2/device_list",
+    ///     json!([{"deviceID": "id", "name": "客厅", "hardware": "LX06"}]),
+    /// );
+    /// let xiaoai = Xiaoai::builder()
+    ///     .transport(mock.clone())
+    ///     .load(b"[]".as_slice())?;
+    ///
+    /// let devices = xiaoai.device_info().await?;
+    /// assert_eq!(devices[0].name, "客厅");
+    ///
+    /// let requests = mock.requests();
+    /// assert_eq!(requests.len(), 1);
+    /// assert_eq!(requests[0].url.path(), "/admin/v2/device_list");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[derive(Clone, Debug, Default)]
+    pub struct MockTransport {
+        state: Arc<Mutex<MockState>>,
+    }
+
+    #[derive(Debug, Default)]
+    struct MockState {
+        responses: HashMap<(Method, String), VecDeque<(StatusCode, String)>>,
+        requests: Vec<MockRequest>,
+        delay: Duration,
+    }
+
+    /// [`MockTransport`] 收到的请求。
+    #[non_exhaustive]
+    #[derive(Clone, Debug)]
+    pub struct MockRequest {
+        /// 请求方法。
+        pub method: Method,
+        /// 完整的请求链接，包括查询参数。
+        pub url: Url,
+        /// 请求体，没有时为空。
+        pub body: Vec<u8>,
+    }
+
+    impl MockRequest {
+        /// 以表单的格式解析请求体，如 [`Xiaoai::post`][crate::Xiaoai::post] 发送的表单。
+        pub fn form(&self) -> HashMap<String, String> {
+            url::form_urlencoded::parse(&self.body)
+                .into_owned()
+                .collect()
+        }
+    }
+
+    impl MockTransport {
+        /// 创建没有任何响应的 `MockTransport`。
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// 设置成功的响应，`data` 会被包装为 `{"code": 0, "message": "Success", "data": data}`。
+        ///
+        /// # Panics
+        ///
+        /// 当内部发生锁中毒时会 panic。
+        pub fn respond(&self, method: Method, path: &str, data: Value) -> &Self {
+            let body = json!({ "code": 0, "message": "Success", "data": data });
+            self.raw_respond(method, path, StatusCode::OK, body.to_string())
+        }
+
+        /// 设置原始的响应，可以用来模拟错误。
+        ///
+        /// # Panics
+        ///
+        /// 当内部发生锁中毒时会 panic。
+        pub fn raw_respond(
+            &self,
+            method: Method,
+            path: &str,
+            status: StatusCode,
+            body: impl Into<String>,
+        ) -> &Self {
+            let key = (method, path.trim_start_matches('/').to_string());
+            self.state
+                .lock()
+                .unwrap()
+                .responses
+                .entry(key)
+                .or_default()
+                .push_back((status, body.into()));
+            self
+        }
+
+        /// 设置每个响应前等待的时间，默认不等待。
+        ///
+        /// 响应在收到请求时就已确定，等待期间发出的其他请求会依次取得之后的响应，可以用来测试并发的请求。
+        ///
+        /// # Panics
+        ///
+        /// 当内部发生锁中毒时会 panic。
+        pub fn delay(&self, delay: Duration) -> &Self {
+            self.state.lock().unwrap().delay = delay;
+            self
+        }
+
+        /// 目前为止收到的所有请求，按收到的顺序排列。
+        ///
+        /// # Panics
+        ///
+        /// 当内部发生锁中毒时会 panic。
+        pub fn requests(&self) -> Vec<MockRequest> {
+            self.state.lock().unwrap().requests.clone()
+        }
+    }
+
+    impl Transport for MockTransport {
+        fn execute(&self, request: Request) -> BoxFuture<'_, crate::Result<Response>> {
+            let mut state = self.state.lock().unwrap();
+            let key = (
+                request.method().clone(),
+                request.url().path().trim_start_matches('/').to_string(),
+            );
+            let (status, body) = match state.responses.get_mut(&key) {
+                Some(responses) if responses.len() > 1 => responses.pop_front().unwrap(),
+                Some(responses) if !responses.is_empty() => responses[0].clone(),
+                _ => (StatusCode::NOT_FOUND, String::new()),
+            };
+            state.requests.push(MockRequest {
+                method: request.method().clone(),
+                url: request.url().clone(),
+                body: request
+                    .body()
+                    .and_then(reqwest::Body::as_bytes)
+                    .unwrap_or_default()
+                    .to_vec(),
+            });
+
+            let response = http::Response::builder()
+                .status(status)
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .body(body)
+                .map(Response::from)
+                .map_err(|err| crate::Error::InvalidInput(err.to_string()));
+
+            let delay = state.delay;
+            if delay.is_zero() {
+                return Box::pin(future::ready(response));
+            }
+
+            Box::pin(async move {
+                tokio::time::sleep(delay).await;
+                response
+            })
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{XiaoaiResponse, util::ubus_info};
+
+/// UBUS 调用的响应，即 [`Xiaoai::ubus_call`][crate::Xiaoai::ubus_call] 响应中的 `data`。
+///
+/// 其中 `code` 是设备上 UBUS 调用的结果，与外层 [`XiaoaiResponse::code`] 不同，
+/// 外层成功时设备上的调用仍可能失败。`info` 是调用实际返回的数据，原本是 JSON 字符串，这里已经解析。
+///
+/// # Examples
+///
+/// ```
+/// # use miai::{UbusResponse, XiaoaiResponse};
+/// # use serde::Deserialize;
+/// # use serde_json::json;
+/// #[derive(Deserialize)]
+/// struct Volume {
+///     volume: u32,
+/// }
+///
+/// let response = XiaoaiResponse {
+///     code: 0,
+///     message: "Success".into(),
+///     data: json!({"code": 0, "info": r#"{"volume":30}"#}),
+/// };
+/// let volume: Volume = UbusResponse::from(response).parse()?;
+/// assert_eq!(volume.volume, 30);
+/// # Ok::<_, miai::Error>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct UbusResponse {
+    /// UBUS 调用的结果，0 表示成功。
+    pub code: i64,
+
+    /// UBUS 调用返回的数据，无法解析时为 [`Value::Null`]。
+    pub info: Value,
+}
+
+impl UbusResponse {
+    /// 校验 `code`，并将 `info` 反序列化为 `T`。
+    ///
+    /// # Errors
+    ///
+    /// `code` 不为 0 时返回 [`Error::Api`][crate::Error::Api]，`info` 不能反序列化为 `T` 时返回
+    /// [`Error::Json`][crate::Error::Json]。
+    pub fn parse<T: DeserializeOwned>(self) -> crate::Result<T> {
+        if self.code != 0 {
+            return Err(crate::Error::Api {
+                code: self.code,
+                message: format!("UBUS 调用失败: {}", self.info),
+            });
+        }
+
+        Ok(serde_json::from_value(self.info)?)
+    }
+}
+
+impl From<XiaoaiResponse> for UbusResponse {
+    /// 从 UBUS 调用的响应中提取，缺少 `code` 时视为成功。
+    fn from(response: XiaoaiResponse) -> Self {
+        Self {
+            code: response
+                .data
+                .get("code")
+                .and_then(Value::as_i64)
+                .unwrap_or_default(),
+            info: ubus_info(&response.data),
+        }
+    }
+}
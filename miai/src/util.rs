@@ -1,8 +1,165 @@
+use std::time::{Duration, Instant};
+
 use rand::{
     distr::{Alphanumeric, SampleString},
     rng,
 };
+use reqwest::{Client, ClientBuilder, Proxy, Request, RequestBuilder, Response, Url};
+use serde_json::Value;
+use tracing::{debug, trace};
+
+use crate::{DEFAULT_APP_VERSION, builder::api_user_agent, login::login_user_agent, redact};
+
+/// 构造 [`Client`] 时的网络选项，参见 [`XiaoaiBuilder::proxy`][crate::XiaoaiBuilder::proxy]。
+#[derive(Clone, Debug, Default)]
+pub struct HttpOptions {
+    pub proxy: Option<Url>,
+    pub accept_invalid_certs: bool,
+    /// 小爱服务请求的 User-Agent，为空时根据 `app_version` 生成。
+    pub user_agent: Option<String>,
+    /// 模拟的米家 App 版本，为空时为 [`DEFAULT_APP_VERSION`]。
+    pub app_version: Option<String>,
+}
+
+impl HttpOptions {
+    /// 应用到 `builder`，代理的地址无效时报错。
+    pub fn apply(&self, mut builder: ClientBuilder) -> crate::Result<ClientBuilder> {
+        // 指定了代理时，环境变量中的代理不再生效
+        if let Some(proxy) = &self.proxy {
+            if !matches!(proxy.scheme(), "http" | "https" | "socks5" | "socks5h") {
+                return Err(crate::Error::InvalidInput(format!(
+                    "不支持的代理 `{proxy}`，只支持 HTTP、HTTPS 和 SOCKS5 代理"
+                )));
+            }
+            builder = builder.proxy(Proxy::all(proxy.clone())?);
+        }
+        if self.accept_invalid_certs {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+
+        Ok(builder)
+    }
+
+    /// 请求小爱服务时的 User-Agent。
+    pub fn api_user_agent(&self) -> String {
+        match &self.user_agent {
+            Some(user_agent) => user_agent.clone(),
+            None => api_user_agent(self.app_version()),
+        }
+    }
+
+    /// 登录时的 User-Agent。
+    pub fn login_user_agent(&self) -> String {
+        login_user_agent(self.app_version())
+    }
+
+    fn app_version(&self) -> &str {
+        self.app_version.as_deref().unwrap_or(DEFAULT_APP_VERSION)
+    }
+}
 
 pub fn random_id(len: usize) -> String {
     Alphanumeric.sample_string(&mut rng(), len)
 }
+
+/// 提取 ubus 响应中的 `info`。
+///
+/// `info` 通常是 JSON 字符串，但也有机型直接返回对象，无法解析时返回 [`Value::Null`]。
+pub fn ubus_info(data: &Value) -> Value {
+    match data.get("info") {
+        Some(Value::String(info)) => serde_json::from_str(info).unwrap_or_default(),
+        Some(info) => info.clone(),
+        None => Value::Null,
+    }
+}
+
+/// 在句末的标点和换行处拆分文本，每段不超过 `max_chars` 个字符。
+///
+/// 相邻的短句会合并为一段，超过 `max_chars` 的句子会被强制拆开，空白的片段会被丢弃。
+pub fn split_sentences(text: &str, max_chars: usize) -> Vec<String> {
+    let max_chars = max_chars.max(1);
+    let mut chunks = Vec::new();
+    let mut chunk = String::new();
+    let mut push = |chunk: &mut String| {
+        let trimmed = chunk.trim();
+        if !trimmed.is_empty() {
+            chunks.push(trimmed.to_string());
+        }
+        chunk.clear();
+    };
+
+    for sentence in text.split_inclusive(['。', '！', '？', '!', '?', '\n']) {
+        let sentence_chars = sentence.chars().count();
+        if chunk.chars().count() + sentence_chars > max_chars {
+            push(&mut chunk);
+        }
+        if sentence_chars <= max_chars {
+            chunk.push_str(sentence);
+            continue;
+        }
+        // 强制拆开过长的句子
+        let chars: Vec<_> = sentence.chars().collect();
+        for part in chars.chunks(max_chars) {
+            chunk.extend(part);
+            if part.len() == max_chars {
+                push(&mut chunk);
+            }
+        }
+    }
+    push(&mut chunk);
+
+    chunks
+}
+
+/// 发送请求，并记录方法、路径、状态码和耗时，以及 trace 级别的请求头和响应头。
+///
+/// 请求头、响应头以及链接中的令牌等敏感信息会被隐藏，返回的错误中也不再包含链接。
+pub async fn execute_logged(client: &Client, request: Request) -> reqwest::Result<Response> {
+    let method = request.method().clone();
+    let url = redact::url(request.url());
+    trace!(
+        "{method} {url} 请求头: {:?}",
+        redact::headers(request.headers())
+    );
+
+    let start = Instant::now();
+    match client.execute(request).await {
+        Ok(response) => {
+            debug!("{method} {url} {} {:?}", response.status(), start.elapsed());
+            // 服务端行为变化时，响应头中的限流计数、服务端版本等有助于排查
+            trace!(
+                "{method} {url} 响应头: {:?}",
+                redact::headers(response.headers())
+            );
+            Ok(response)
+        }
+        Err(err) => {
+            let err = err.without_url();
+            debug!("{method} {url} 失败 {:?}: {err}", start.elapsed());
+            Err(err)
+        }
+    }
+}
+
+pub trait RequestBuilderExt {
+    /// 仅在 `timeout` 存在时设置超时。
+    fn timeout_opt(self, timeout: Option<Duration>) -> Self;
+
+    /// 同 [`RequestBuilder::send`]，但会记录日志，参见 [`execute_logged`]。
+    async fn send_logged(self) -> reqwest::Result<Response>;
+}
+
+impl RequestBuilderExt for RequestBuilder {
+    fn timeout_opt(self, timeout: Option<Duration>) -> Self {
+        match timeout {
+            Some(timeout) => self.timeout(timeout),
+            None => self,
+        }
+    }
+
+    async fn send_logged(self) -> reqwest::Result<Response> {
+        let (client, request) = self.build_split();
+
+        execute_logged(&client, request?).await
+    }
+}
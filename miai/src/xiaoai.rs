@@ -1,65 +1,313 @@
 use std::{
-    collections::HashMap,
-    io::{BufRead, Write},
-    sync::Arc,
+    collections::{HashMap, HashSet},
+    fmt,
+    io::{self, BufRead, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
-use cookie_store::{
-    RawCookie,
-    serde::json::{load_all, save_incl_expired_and_nonpersistent},
+use cookie_store::{CookieExpiration, RawCookie};
+use futures_util::{
+    Stream, StreamExt, TryStreamExt,
+    future::{self, join_all},
+    stream,
+};
+use reqwest::{
+    Client, Method, RequestBuilder, StatusCode, Url,
+    cookie::CookieStore,
+    header::{COOKIE, HeaderValue, SET_COOKIE, USER_AGENT},
 };
-use reqwest::{Client, Url};
 use reqwest_cookie_store::CookieStoreMutex;
-use serde::{Deserialize, Serialize};
-use serde_json::json;
-use time::OffsetDateTime;
-use tracing::trace;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Value, json};
+use time::{OffsetDateTime, Time, UtcOffset};
+use tracing::{debug, trace, warn};
+
+use crate::{
+    AuthState, CachedDeviceInfo, Capabilities, Device, DeviceModel, NlpResult, PlayMode, PlayState,
+    PlaybackState, PlayerStatus, Transport, XiaoaiBuilder, XiaoaiResponse,
+    alarm::Alarm,
+    auth_file, conversation,
+    device_cache::DeviceCache,
+    dnd::Dnd,
+    login::{Login, QrCode},
+    miot::{self, Property},
+    rate_limit::RateLimiter,
+    redact,
+    retry::RetryPolicy,
+    serve::FileServer,
+    util::{HttpOptions, RequestBuilderExt, random_id, split_sentences, ubus_info},
+};
+
+/// 无法得知静音前的音量时，[`Xiaoai::unmute`] 恢复到的音量。
+pub const DEFAULT_UNMUTE_VOLUME: u32 = 30;
 
-use crate::{XiaoaiResponse, conversation, login::Login, util::random_id};
+/// 音量的最大值，参见 [`Xiaoai::set_volume`]。
+pub const MAX_VOLUME: u32 = 100;
 
-const API_SERVER: &str = "https://api2.mina.mi.com/";
-const API_UA: &str = "MiHome/6.0.103 (com.xiaomi.mihome; build:6.0.103.1; iOS 14.4.0) Alamofire/6.0.103 MICO/iOSApp/appStore/6.0.103";
+/// [`Xiaoai::ramp_volume`] 建议的步数，在平滑和请求数之间取得平衡。
+pub const DEFAULT_RAMP_STEPS: u32 = 10;
+
+/// 设备名称的最大字符数，参见 [`Xiaoai::rename_device`]。
+pub const MAX_DEVICE_NAME_CHARS: usize = 20;
+
+/// [`Xiaoai::tts_chunked`] 每段文本的最大字符数。
+pub const TTS_CHUNK_CHARS: usize = 100;
 
 /// 提供小爱服务请求。
 ///
 /// `Xiaoai` 代表着一个账号的登录状态，但如果需要重用的话，也无需再包一层
 /// [`std::rc::Rc`] 或 [`Arc`]，`Xiaoai` 已经在内部使用 [`Arc`] 共享状态。
+///
+/// `Xiaoai` 是 `Send + Sync` 的，所有方法都只需要 `&self`，可以直接克隆到多个任务中并发调用，
+/// 无需额外加锁。会变化的共享状态，如 Cookies、设备列表缓存和静音前的音量，都由内部的锁保护。
+/// 多个请求同时发现登录失效时，只有一个会去刷新，其他请求等待刷新完成后直接重试。
+///
+/// # Examples
+///
+/// ```
+/// # use miai::Xiaoai;
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() -> miai::Result<()> {
+/// let xiaoai = Xiaoai::load(b"[]".as_slice())?.with_dry_run(true);
+/// let tasks: Vec<_> = (0..8)
+///     .map(|i| {
+///         let xiaoai = xiaoai.clone();
+///         tokio::spawn(async move { xiaoai.tts("device_id", &i.to_string()).await })
+///     })
+///     .collect();
+/// for task in tasks {
+///     task.await.unwrap()?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
 #[derive(Clone, Debug)]
 pub struct Xiaoai {
-    client: Client,
-    cookie_store: Arc<CookieStoreMutex>,
-    server: Url,
+    pub(crate) client: Client,
+    /// 实际发送请求的 HTTP 层，默认即是 `client`。
+    pub(crate) transport: Arc<dyn Transport>,
+    /// `client` 是外部传入的，需要自行处理 Cookies。
+    pub(crate) attach_cookies: bool,
+    /// 需要自行附带的 User-Agent，参见 [`XiaoaiBuilder::user_agent`]。
+    pub(crate) user_agent: HeaderValue,
+    pub(crate) cookie_store: Arc<CookieStoreMutex>,
+    pub(crate) server: Url,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) retry: RetryPolicy,
+    pub(crate) rate_limiter: Option<RateLimiter>,
+    /// 返回时间时使用的时区偏移。
+    pub(crate) utc_offset: UtcOffset,
+    pub(crate) device_cache: Option<DeviceCache>,
+    /// 静音前的音量，以设备 ID 为键。
+    pub(crate) muted_volumes: Arc<Mutex<HashMap<String, u32>>>,
+    pub(crate) refresh_hook: Option<RefreshHook>,
+    /// 代替登录服务刷新登录状态，参见 [`XiaoaiBuilder::mock_refresh`]。
+    #[cfg(feature = "test-util")]
+    pub(crate) mock_refresh: Option<MockRefresh>,
+    /// 已自动刷新登录状态的次数，同时用于保证同一时间只有一个请求在刷新。
+    pub(crate) refresh_generation: Arc<tokio::sync::Mutex<u64>>,
+    /// 刷新和注销登录时构造 `Client` 的网络选项。
+    pub(crate) http: HttpOptions,
+    /// 只记录请求而不发送，参见 [`Xiaoai::with_dry_run`]。
+    pub(crate) dry_run: bool,
 }
 
 impl Xiaoai {
     /// 登录以调用小爱服务。
     pub async fn login(username: &str, password: &str) -> crate::Result<Self> {
-        let login = Login::new(username, password)?;
-        let login_response = login.login().await?;
-        let auth_response = login.auth(login_response).await?;
-        login.get_token(auth_response).await?;
+        Self::builder().login(username, password).await
+    }
 
-        Self::from_login(login)
+    /// 扫码登录以调用小爱服务，参见 [`XiaoaiBuilder::login_qr`]。
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use miai::Xiaoai;
+    /// # async fn login() -> miai::Result<()> {
+    /// let xiaoai = Xiaoai::login_qr(|qr_code| {
+    ///     println!("请用米家 App 扫描二维码: {}", qr_code.image_url);
+    /// })
+    /// .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn login_qr(on_qr_code: impl FnOnce(&QrCode)) -> crate::Result<Self> {
+        Self::builder().login_qr(on_qr_code).await
     }
 
     /// 从 [`Login`][`crate::login::Login`] 构造。
     pub fn from_login(login: Login) -> crate::Result<Self> {
-        let cookie_store = login.into_cookie_store();
-        let client = Client::builder()
-            .user_agent(API_UA)
-            .cookie_provider(cookie_store.clone())
-            .build()?;
-
-        Ok(Self {
-            client,
-            cookie_store,
-            server: Url::parse(API_SERVER)?,
-        })
+        Self::builder().from_login(login)
+    }
+
+    /// 返回时间时使用的时区偏移，参见 [`XiaoaiBuilder::utc_offset`]。
+    pub fn utc_offset(&self) -> UtcOffset {
+        self.utc_offset
+    }
+
+    /// 创建 [`XiaoaiBuilder`] 以进行更细致的配置。
+    pub fn builder() -> XiaoaiBuilder {
+        XiaoaiBuilder::new()
     }
 
     /// 列出所有设备的信息。
+    ///
+    /// 结果按名称稳定排序，名称相同时按设备 ID 排序，每次调用的顺序都一致，可以依赖下标。
+    /// 重新绑定后服务端可能返回重复的设备，只保留同一设备 ID 中最后出现的一个。
+    ///
+    /// 启用了 [`XiaoaiBuilder::device_cache`] 时，缓存未过期则直接返回缓存的结果。
     pub async fn device_info(&self) -> crate::Result<Vec<DeviceInfo>> {
-        self.raw_device_info().await?.extract_data()
+        match &self.device_cache {
+            Some(cache) => cache.get_or_fetch(|| self.fetch_device_info()).await,
+            None => self.fetch_device_info().await,
+        }
+    }
+
+    /// 使设备列表的缓存失效，并重新获取设备列表。
+    ///
+    /// 没有启用 [`XiaoaiBuilder::device_cache`] 时同 [`Self::device_info`]。
+    pub async fn refresh_devices(&self) -> crate::Result<Vec<DeviceInfo>> {
+        if let Some(cache) = &self.device_cache {
+            cache.invalidate().await;
+        }
+
+        self.device_info().await
+    }
+
+    /// 获取设备列表并保存到文件 `path`，之后可以用 [`Self::device_info_cached`] 在不发出请求的情况下读取。
+    ///
+    /// 返回获取到的设备列表，同 [`Self::device_info`]。演练模式下不会写入文件。
+    ///
+    /// # Errors
+    ///
+    /// 获取设备列表失败时不会写入文件。写入失败时返回 [`Error::Io`][crate::Error::Io]。
+    pub async fn cache_device_info(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> crate::Result<Vec<DeviceInfo>> {
+        let cached = CachedDeviceInfo::new(self.device_info().await?);
+        if self.dry_run {
+            return Ok(cached.devices);
+        }
+        let json = serde_json::to_vec_pretty(&cached)?;
+        tokio::fs::write(path, json).await?;
+
+        Ok(cached.devices)
+    }
+
+    /// 读取 [`Self::cache_device_info`] 保存的设备列表，不需要登录，也不会发出请求。
+    ///
+    /// 缓存可能已经过时，可以通过 [`CachedDeviceInfo::age`] 判断。
+    ///
+    /// # Errors
+    ///
+    /// 文件不存在或无法读取时返回 [`Error::Io`][crate::Error::Io]，格式无效时返回
+    /// [`Error::Json`][crate::Error::Json]。
+    pub fn device_info_cached(path: impl AsRef<Path>) -> crate::Result<CachedDeviceInfo> {
+        let json = std::fs::read(path)?;
+
+        Ok(serde_json::from_slice(&json)?)
+    }
+
+    /// 绑定设备 ID，得到一个 [`Device`]，之后调用时无需再传入设备 ID。
+    ///
+    /// 不会发出请求，也不会检查设备是否存在。
+    pub fn device(&self, device_id: impl Into<String>) -> Device<'_> {
+        Device::new(self, device_id.into())
+    }
+
+    /// 列出所有设备，顺序同 [`Self::device_info`]。
+    pub async fn devices(&self) -> crate::Result<Vec<Device<'_>>> {
+        let device_info = self.device_info().await?;
+
+        Ok(device_info
+            .into_iter()
+            .map(|info| self.device(info.device_id))
+            .collect())
+    }
+
+    async fn fetch_device_info(&self) -> crate::Result<Vec<DeviceInfo>> {
+        let mut device_info: Vec<DeviceInfo> = self.raw_device_info().await?.extract_data()?;
+
+        // 去重时保留最后出现的，即服务端较新的记录
+        let mut seen = HashSet::new();
+        device_info.reverse();
+        device_info.retain(|info| seen.insert(info.device_id.clone()));
+        device_info.sort_by(|a, b| (&a.name, &a.device_id).cmp(&(&b.name, &b.device_id)));
+
+        Ok(device_info)
+    }
+
+    /// 获取单个设备的信息。
+    ///
+    /// 服务器不支持按 ID 查询，因此仍会请求整个设备列表。
+    ///
+    /// # Errors
+    ///
+    /// 找不到设备时返回 [`Error::DeviceNotFound`][crate::Error::DeviceNotFound]。
+    /// 传入的是某个设备的 [`miio_did`][DeviceInfo::miio_did] 时返回
+    /// [`Error::MiioDidGiven`][crate::Error::MiioDidGiven]，其中带有应使用的设备 ID。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "test-util")]
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> miai::Result<()> {
+    /// # use std::time::Duration;
+    /// # use miai::{Error, MockTransport, Xiaoai};
+    /// # use reqwest::{Method, StatusCode};
+    /// # use serde_json::json;
+    /// let mock = MockTransport::new();
+    /// mock.respond(
+    ///     Method::GET,
+    ///     "admin/v2/device_list",
+    ///     // 有的响应使用 `miotDID`，did 也可能是数字
+    ///     json!([{"deviceID": "mina-id", "miotDID": 123456, "name": "客厅", "hardware": "LX06"}]),
+    /// );
+    /// let xiaoai = Xiaoai::builder()
+    ///     .transport(mock.clone())
+    ///     .device_cache(Duration::from_secs(60))
+    ///     .load(b"[]".as_slice())?;
+    ///
+    /// let info = xiaoai.device_info_one("mina-id").await?;
+    /// assert_eq!(info.miio_did.as_deref(), Some("123456"));
+    ///
+    /// let result = xiaoai.device_info_one("123456").await;
+    /// assert!(matches!(result, Err(Error::MiioDidGiven { device_id, .. }) if device_id == "mina-id"));
+    ///
+    /// // 设备列表已经缓存时，其他方法失败后同样能识别出 miio did
+    /// mock.raw_respond(
+    ///     Method::POST,
+    ///     "remote/ubus",
+    ///     StatusCode::OK,
+    ///     r#"{"code": 101, "message": "device not found", "data": null}"#,
+    /// );
+    /// let result = xiaoai.tts("123456", "你好").await;
+    /// assert!(matches!(result, Err(Error::MiioDidGiven { .. })));
+    /// # Ok(())
+    /// # }
+    /// # #[cfg(not(feature = "test-util"))]
+    /// # fn main() {}
+    /// ```
+    pub async fn device_info_one(&self, device_id: &str) -> crate::Result<DeviceInfo> {
+        let device_info = self.device_info().await?;
+        if let Some(info) = device_info.iter().find(|info| info.device_id == device_id) {
+            return Ok(info.clone());
+        }
+        match device_info
+            .into_iter()
+            .find(|info| info.miio_did.as_deref() == Some(device_id))
+        {
+            Some(info) => Err(crate::Error::MiioDidGiven {
+                did: device_id.to_string(),
+                device_id: info.device_id,
+            }),
+            None => Err(crate::Error::DeviceNotFound(device_id.to_string())),
+        }
     }
 
     /// 同 [`Self::device_info`]，但返回原始的响应。
@@ -70,9 +318,75 @@ impl Xiaoai {
         Ok(response)
     }
 
+    /// 重命名设备，名称的首尾空白会被去除。
+    ///
+    /// # Errors
+    ///
+    /// 名称为空或超过 [`MAX_DEVICE_NAME_CHARS`] 个字符时，返回 [`Error::InvalidInput`][crate::Error::InvalidInput]。
+    pub async fn rename_device(
+        &self,
+        device_id: &str,
+        name: &str,
+    ) -> crate::Result<XiaoaiResponse> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(crate::Error::InvalidInput("设备名称不能为空".into()));
+        }
+        if name.chars().count() > MAX_DEVICE_NAME_CHARS {
+            return Err(crate::Error::InvalidInput(format!(
+                "设备名称不能超过 {MAX_DEVICE_NAME_CHARS} 个字符"
+            )));
+        }
+        let form = HashMap::from([("deviceId", device_id), ("name", name)]);
+        let response = self.post("admin/v2/device_rename", form).await?;
+        if let Some(cache) = &self.device_cache {
+            cache.invalidate().await;
+        }
+
+        Ok(response)
+    }
+
+    /// 同时对多个设备执行请求。
+    ///
+    /// 对 `device_ids` 中的每个设备调用 `f`，并发地等待所有请求完成，再按原有顺序返回每个设备的结果。
+    /// 单个设备出错不会影响其他设备。
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use miai::Xiaoai;
+    /// # async fn broadcast(xiaoai: Xiaoai) {
+    /// let results = xiaoai
+    ///     .broadcast(&["客厅", "卧室"], |xiaoai, device_id| xiaoai.tts(device_id, "晚饭好了"))
+    ///     .await;
+    /// for (device_id, result) in results {
+    ///     if let Err(err) = result {
+    ///         eprintln!("{device_id} 播报失败: {err}");
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub async fn broadcast<'s, 'a, F, Fut, T>(
+        &'s self,
+        device_ids: &[&'a str],
+        f: F,
+    ) -> Vec<(&'a str, crate::Result<T>)>
+    where
+        F: Fn(&'s Xiaoai, &'a str) -> Fut,
+        Fut: Future<Output = crate::Result<T>>,
+    {
+        let tasks = device_ids.iter().map(|&device_id| {
+            let task = f(self, device_id);
+            async move { (device_id, task.await) }
+        });
+
+        join_all(tasks).await
+    }
+
     /// 返回内部使用的 [`reqwest::Client`]。
     ///
     /// 该 `Client` 会共享登录状态，可以用来做一些 `Xiaoai` 没有提供的更底层的请求。
+    /// 但如果 `Client` 是通过 [`XiaoaiBuilder::client`] 传入的，则不会携带登录状态。
     pub fn client(&self) -> &Client {
         &self.client
     }
@@ -84,17 +398,10 @@ impl Xiaoai {
         let request_id = random_request_id();
         let url =
             Url::parse_with_params(self.server.join(uri)?.as_str(), [("requestId", request_id)])?;
-        let response = self
-            .client
-            .get(url)
-            .send()
-            .await?
-            .error_for_status()?
-            .json::<XiaoaiResponse>()
-            .await?
-            .error_for_code()?;
 
-        Ok(response)
+        self.send(|| self.client.get(url.clone()))
+            .await?
+            .error_for_code()
     }
 
     /// 小爱服务的通用 POST 请求。
@@ -108,51 +415,484 @@ impl Xiaoai {
         let request_id = random_request_id();
         form.insert("requestId", &request_id);
         let url = self.server.join(uri)?;
+
+        self.send(|| self.client.post(url.clone()).form(&form))
+            .await?
+            .error_for_code()
+    }
+
+    /// 小爱服务的通用请求，用于调用 `Xiaoai` 尚未封装的接口。
+    ///
+    /// 与 [`Self::get`]、[`Self::post`] 一样会附带登录状态和 `requestId`，并在登录失效时自动刷新，
+    /// 校验错误码后返回响应的 `data`。`body` 为 JSON 对象时以表单的形式发送，字符串以外的值会被序列化为 JSON 文本。
+    ///
+    /// **不稳定**：该接口用于探索未公开的 API，签名和行为可能在之后的版本中改变。
+    ///
+    /// # Errors
+    ///
+    /// `body` 不是 JSON 对象时，返回 [`Error::InvalidInput`][crate::Error::InvalidInput]。
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use miai::Xiaoai;
+    /// # use serde_json::json;
+    /// # async fn request(xiaoai: Xiaoai) -> miai::Result<()> {
+    /// let body = json!({"deviceId": "<DEVICE_ID>", "name": "客厅"});
+    /// let data = xiaoai
+    ///     .request(reqwest::Method::POST, "admin/v2/device_rename", Some(&body))
+    ///     .await?;
+    /// println!("{data}");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn request(
+        &self,
+        method: Method,
+        uri: &str,
+        body: Option<&Value>,
+    ) -> crate::Result<Value> {
+        let form: Option<Vec<(&str, String)>> = match body {
+            Some(Value::Object(body)) => Some(
+                body.iter()
+                    .map(|(key, value)| match value {
+                        Value::String(value) => (key.as_str(), value.clone()),
+                        value => (key.as_str(), value.to_string()),
+                    })
+                    .collect(),
+            ),
+            Some(_) => {
+                return Err(crate::Error::InvalidInput("请求体必须是 JSON 对象".into()));
+            }
+            None => None,
+        };
+        let request_id = random_request_id();
+        let url =
+            Url::parse_with_params(self.server.join(uri)?.as_str(), [("requestId", request_id)])?;
+
         let response = self
-            .client
-            .post(url)
-            .form(&form)
-            .send()
+            .send(|| {
+                let request = self.client.request(method.clone(), url.clone());
+                match &form {
+                    Some(form) => request.form(form),
+                    None => request,
+                }
+            })
             .await?
+            .error_for_code()?;
+
+        Ok(response.data)
+    }
+
+    /// 刷新登录状态。
+    ///
+    /// 利用登录时留下的 `passToken` 重新获取 `serviceToken`，无需账号密码。
+    /// 刷新成功后会调用 [`Self::on_refresh`] 注册的回调。
+    /// 一般无需手动调用，请求遇到登录失效时会自动刷新并重试一次；也可以根据
+    /// [`Self::token_expiry`] 在过期前主动刷新。
+    ///
+    /// # Errors
+    ///
+    /// 无法刷新时返回 [`Error::SessionExpired`][crate::Error::SessionExpired]，此时需要重新
+    /// [`login`][Self::login]。
+    pub async fn refresh(&self) -> crate::Result<()> {
+        debug!("登录状态失效，尝试刷新");
+        #[cfg(feature = "test-util")]
+        if let Some(mock) = &self.mock_refresh {
+            (mock.0)()?;
+            if let Some(hook) = &self.refresh_hook {
+                (hook.0)(self);
+            }
+            return Ok(());
+        }
+        let mut login = Login::from_cookie_store(Arc::clone(&self.cookie_store))?
+            .with_http_options(&self.http)?;
+        if let Some(timeout) = self.timeout {
+            login = login.with_timeout(timeout);
+        }
+        let auth_response = login.refresh().await?;
+        login
+            .get_token(auth_response)
+            .await
+            .map_err(|_| crate::Error::SessionExpired)?;
+        if let Some(hook) = &self.refresh_hook {
+            (hook.0)(self);
+        }
+
+        Ok(())
+    }
+
+    /// 注销登录，使服务器上的登录状态失效。
+    ///
+    /// 无论注销请求是否成功，本地的登录状态都会被清空，所有共享状态的 `Xiaoai` 都将无法再使用。
+    /// 如果此前 [`save`][Self::save] 过登录状态，也请一并删除。
+    ///
+    /// # Errors
+    ///
+    /// 注销请求失败时报错，此时服务器上的登录状态可能仍然有效。
+    ///
+    /// # Panics
+    ///
+    /// 当内部发生锁中毒时会 panic。
+    pub async fn logout(self) -> crate::Result<()> {
+        let mut login = Login::from_cookie_store(Arc::clone(&self.cookie_store))?
+            .with_http_options(&self.http)?;
+        if let Some(timeout) = self.timeout {
+            login = login.with_timeout(timeout);
+        }
+        let result = login.logout().await;
+        self.cookie_store.lock().unwrap().clear();
+
+        result
+    }
+
+    /// 注册刷新登录状态后的回调。
+    ///
+    /// 可以在回调中 [`save`][Self::save] 新的登录状态，以便下次 [`load`][Self::load] 时使用。
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::fs::File;
+    /// # use miai::Xiaoai;
+    /// # fn on_load(xiaoai: Xiaoai) {
+    /// let xiaoai = xiaoai.on_refresh(|xiaoai| {
+    ///     let mut file = File::create("xiaoai-auth.json").unwrap();
+    ///     xiaoai.save(&mut file).unwrap();
+    /// });
+    /// # }
+    /// ```
+    pub fn on_refresh<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&Xiaoai) + Send + Sync + 'static,
+    {
+        self.refresh_hook = Some(RefreshHook(Arc::new(hook)));
+        self
+    }
+
+    /// 启用或关闭演练模式。
+    ///
+    /// 演练模式下不会真正发送小爱服务的请求，而是在 `debug` 级别记录请求的方法、链接和表单，
+    /// 并返回一个合成的成功响应，其 `data` 中包含这些信息：
+    ///
+    /// ```json
+    /// {"dryRun": true, "method": "POST", "url": "...", "form": {"deviceId": "...", ...}}
+    /// ```
+    ///
+    /// 依赖响应数据的方法，如 [`Self::player_status`]，在演练模式下会因为数据不符合预期而出错。
+    /// 先读取状态再修改的方法，如 [`Self::adjust_volume`] 也是如此。
+    /// 返回的 `Xiaoai` 与原来的共享登录状态，因此可以同时使用两者，只在需要时演练。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use miai::Xiaoai;
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> miai::Result<()> {
+    /// # let xiaoai = Xiaoai::load(b"[]".as_slice())?;
+    /// let response = xiaoai.with_dry_run(true).tts("device_id", "你好").await?;
+    /// assert_eq!(response.data["form"]["method"], "text_to_speech");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// 是否处于演练模式，参见 [`Self::with_dry_run`]。
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// 发送请求，遇到登录失效时自动刷新并重试一次。
+    async fn send<F>(&self, build: F) -> crate::Result<XiaoaiResponse>
+    where
+        F: Fn() -> RequestBuilder,
+    {
+        // 正在刷新时等待刷新完成，再以刷新后的登录状态发送
+        let generation = *self.refresh_generation.lock().await;
+        match self.send_with_retry(&build).await {
+            Err(crate::Error::SessionExpired) => {
+                self.refresh_once(generation).await?;
+                self.send_with_retry(&build).await
+            }
+            result => result,
+        }
+    }
+
+    /// 刷新登录状态，除非发送请求后已经有其他请求刷新过了。
+    async fn refresh_once(&self, generation: u64) -> crate::Result<()> {
+        let mut current = self.refresh_generation.lock().await;
+        if *current == generation {
+            self.refresh().await?;
+            *current += 1;
+        }
+
+        Ok(())
+    }
+
+    /// 发送请求，遇到暂时性的错误时按照重试策略重试。
+    async fn send_with_retry<F>(&self, build: &F) -> crate::Result<XiaoaiResponse>
+    where
+        F: Fn() -> RequestBuilder,
+    {
+        let mut attempt = 1;
+        loop {
+            match self.send_once(build).await {
+                Err(err) if err.is_transient() && attempt < self.retry.max_attempts => {
+                    let delay = self.retry.delay(attempt);
+                    debug!("第 {attempt} 次请求失败，{delay:?} 后重试: {err}");
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn send_once<F>(&self, build: &F) -> crate::Result<XiaoaiResponse>
+    where
+        F: Fn() -> RequestBuilder,
+    {
+        if self.dry_run {
+            return Ok(dry_run_response(build().build()?));
+        }
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire().await;
+        }
+        let mut request = build().timeout_opt(self.timeout).build()?;
+        let url = request.url().clone();
+        if self.attach_cookies {
+            let headers = request.headers_mut();
+            headers.insert(USER_AGENT, self.user_agent.clone());
+            if let Some(cookies) = self.cookie_store.cookies(&url) {
+                headers.insert(COOKIE, cookies);
+            }
+        }
+        let response = self.transport.execute(request).await?;
+        if self.attach_cookies {
+            let mut set_cookies = response.headers().get_all(SET_COOKIE).iter();
+            self.cookie_store.set_cookies(&mut set_cookies, &url);
+        }
+        if response.status() == StatusCode::UNAUTHORIZED {
+            return Err(crate::Error::SessionExpired);
+        }
+        let response = response
             .error_for_status()?
             .json::<XiaoaiResponse>()
-            .await?
-            .error_for_code()?;
+            .await?;
+        trace!("响应: {} {}", response.code, response.message);
+        if response.code == i64::from(StatusCode::UNAUTHORIZED.as_u16()) {
+            return Err(crate::Error::SessionExpired);
+        }
 
         Ok(response)
     }
 
     /// 保存登录状态到 `writer`。
     ///
-    /// 状态被保存为明文的 json，请注意安全性。参见
-    /// [`cookie_store::serde::json::save_incl_expired_and_nonpersistent`]。
+    /// 状态被保存为明文的 json，包括登录服务的 `passToken` 在内的所有 Cookies，请注意安全性。
+    /// 其中带有格式的版本号，[`Xiaoai::load`] 可以加载旧版本保存的状态。
+    ///
+    /// 同 [`Xiaoai::to_writer`]，但错误统一为 [`Error::Io`][crate::Error::Io]。
+    ///
+    /// # Panics
+    ///
+    /// 当内部发生锁中毒时会 panic。
+    pub fn save<W: Write>(&self, writer: &mut W) -> crate::Result<()> {
+        Ok(self.to_writer(writer)?)
+    }
+
+    /// 保存登录状态到 `writer`，格式同 [`Xiaoai::save`]。
+    ///
+    /// 返回写入时的 IO 错误，比如磁盘已满或没有写入权限。登录状态一般总是可以序列化的，
+    /// 万一序列化失败，不会写入任何内容，并返回 [`ErrorKind::InvalidData`][io::ErrorKind::InvalidData] 的错误。
+    ///
+    /// # Panics
+    ///
+    /// 当内部发生锁中毒时会 panic。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::io::ErrorKind;
+    /// # use miai::Xiaoai;
+    /// let xiaoai = Xiaoai::load(b"[]".as_slice())?;
+    ///
+    /// let mut saved = Vec::new();
+    /// xiaoai.to_writer(&mut saved)?;
+    /// assert!(Xiaoai::load(saved.as_slice()).is_ok());
+    ///
+    /// // 写不下时返回 IO 错误
+    /// let mut full = [0; 4];
+    /// let err = xiaoai.to_writer(full.as_mut_slice()).unwrap_err();
+    /// assert_eq!(err.kind(), ErrorKind::WriteZero);
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn to_writer(&self, writer: impl Write) -> io::Result<()> {
+        auth_file::to_writer(&self.auth_state(), writer)
+    }
+
+    /// 当前登录账号的小米 ID，读取自登录时保存的 Cookies，不会发出请求。
+    ///
+    /// 可以用来确认认证文件属于哪个账号。服务端没有在登录状态中提供昵称等信息，
+    /// Cookies 中没有账号 ID 时返回 `None`，比如 [`Xiaoai::logout`] 之后。
+    ///
+    /// # Panics
+    ///
+    /// 当内部发生锁中毒时会 panic。
+    pub fn user_id(&self) -> Option<String> {
+        self.cookie_store
+            .lock()
+            .unwrap()
+            .iter_any()
+            .find(|cookie| cookie.name() == "userId" && !cookie.value().is_empty())
+            .map(|cookie| cookie.value().to_string())
+    }
+
+    /// 当前 `serviceToken` 的过期时间，读取自 Cookies，不会发出请求。
+    ///
+    /// 长时间运行的服务可以在空闲时提前 [`refresh`][Self::refresh]，避免在操作中途刷新。
+    /// 刷新后会得到新的过期时间。已经过期时返回过去的时间。
+    ///
+    /// 服务端通常把 `serviceToken` 设为会话 Cookie，不提供过期时间，此时返回 `None`，
+    /// 只能依靠请求遇到登录失效时的自动刷新。没有登录状态时也返回 `None`。
+    ///
+    /// # Panics
+    ///
+    /// 当内部发生锁中毒时会 panic。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use miai::{Xiaoai, time::{Duration, OffsetDateTime}};
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> miai::Result<()> {
+    /// let xiaoai = Xiaoai::load(b"[]".as_slice())?;
+    /// assert_eq!(xiaoai.token_expiry(), None);
+    ///
+    /// // 十分钟内过期时提前刷新
+    /// let soon = OffsetDateTime::now_utc() + Duration::minutes(10);
+    /// if xiaoai.token_expiry().is_some_and(|expiry| expiry < soon) {
+    ///     xiaoai.refresh().await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn token_expiry(&self) -> Option<OffsetDateTime> {
+        self.cookie_store
+            .lock()
+            .unwrap()
+            .iter_any()
+            .filter(|cookie| cookie.name() == "serviceToken" && !cookie.value().is_empty())
+            .filter_map(|cookie| match cookie.expires {
+                CookieExpiration::AtUtc(expiry) => Some(expiry),
+                CookieExpiration::SessionEnd => None,
+            })
+            .min()
+    }
+
+    /// 获取可序列化的登录状态，可以自行保存到任意位置，参见 [`AuthState`]。
+    ///
+    /// [`Xiaoai::save`] 即是以 JSON 格式保存它。
     ///
     /// # Panics
     ///
     /// 当内部发生锁中毒时会 panic。
-    pub fn save<W: Write>(&self, writer: &mut W) -> cookie_store::Result<()> {
-        save_incl_expired_and_nonpersistent(&self.cookie_store.lock().unwrap(), writer)
+    pub fn auth_state(&self) -> AuthState {
+        AuthState::from_cookie_store(&self.cookie_store.lock().unwrap())
+    }
+
+    /// 从 [`AuthState`] 恢复登录状态。
+    ///
+    /// 与 [`Xiaoai::load`] 一样**不会**验证登录状态的有效性。
+    pub fn from_auth_state(auth_state: AuthState) -> crate::Result<Self> {
+        Self::builder().from_auth_state(auth_state)
     }
 
     /// 从 `reader` 加载登录状态。
     ///
     /// **不会**验证登录状态的有效性，如果在请求时出错，请尝试重新
-    /// [`login`][Self::login]。另请参见 [`cookie_store::serde::json::load_all`]。
-    pub fn load<R: BufRead>(reader: R) -> cookie_store::Result<Self> {
-        let cookie_store = Arc::new(CookieStoreMutex::new(load_all(reader)?));
-        let client = Client::builder()
-            .user_agent(API_UA)
-            .cookie_provider(Arc::clone(&cookie_store))
-            .build()?;
-
-        Ok(Self {
-            client,
-            cookie_store,
-            server: Url::parse(API_SERVER)?,
-        })
+    /// [`login`][Self::login]。格式参见 [`Xiaoai::save`]。
+    ///
+    /// 旧版本的状态加载后再 [`save`][Self::save] 即可迁移到当前版本。
+    ///
+    /// # Errors
+    ///
+    /// 状态的版本未知时，返回 [`Error::UnsupportedAuthVersion`][crate::Error::UnsupportedAuthVersion]。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use miai::{Error, Xiaoai};
+    /// // 版本 1 是不带版本号的 Cookies 数组，加载后保存为当前版本
+    /// let v1 = r#"[
+    ///     {
+    ///         "raw_cookie": "serviceToken=token; Path=/; Domain=mina.mi.com",
+    ///         "path": ["/", true],
+    ///         "domain": {"Suffix": "mina.mi.com"},
+    ///         "expires": "SessionEnd"
+    ///     },
+    ///     {
+    ///         "raw_cookie": "userId=10086; Path=/; Domain=mina.mi.com",
+    ///         "path": ["/", true],
+    ///         "domain": {"Suffix": "mina.mi.com"},
+    ///         "expires": "SessionEnd"
+    ///     }
+    /// ]"#;
+    /// let xiaoai = Xiaoai::load(v1.as_bytes())?;
+    /// assert_eq!(xiaoai.user_id().as_deref(), Some("10086"));
+    /// let mut saved = Vec::new();
+    /// xiaoai.save(&mut saved)?;
+    /// assert!(String::from_utf8_lossy(&saved).contains(r#""version": 2"#));
+    ///
+    /// // 迁移后登录状态不变
+    /// let migrated = Xiaoai::load(saved.as_slice())?;
+    /// assert_eq!(migrated.user_id().as_deref(), Some("10086"));
+    /// assert!(String::from_utf8_lossy(&saved).contains("serviceToken=token"));
+    ///
+    /// // 当前版本可以原样加载
+    /// let xiaoai = Xiaoai::load(saved.as_slice())?;
+    /// let mut resaved = Vec::new();
+    /// xiaoai.save(&mut resaved)?;
+    /// assert_eq!(saved, resaved);
+    ///
+    /// // 未知的版本
+    /// let result = Xiaoai::load(br#"{"version": 99, "cookies": []}"#.as_slice());
+    /// assert!(matches!(result, Err(Error::UnsupportedAuthVersion(99))));
+    /// # Ok::<_, Error>(())
+    /// ```
+    pub fn load<R: BufRead>(reader: R) -> crate::Result<Self> {
+        Self::builder().load(reader)
     }
 
     /// 向小爱设备发送 OpenWrt UBUS 调用请求。
+    ///
+    /// 返回原始的响应，可以转换为 [`UbusResponse`][crate::UbusResponse] 以校验设备上的调用结果并解析数据。
+    ///
+    /// # Errors
+    ///
+    /// `message` 是 UBUS 方法的参数，必须是有效的 JSON，否则在发送前返回
+    /// [`Error::InvalidInput`][crate::Error::InvalidInput]，并指出出错的位置。
+    ///
+    /// 服务端返回错误、且 `device_id` 是 [`XiaoaiBuilder::device_cache`] 中某个设备的
+    /// [`miio_did`][DeviceInfo::miio_did] 时，返回 [`Error::MiioDidGiven`][crate::Error::MiioDidGiven]。
+    /// [`Self::tts`]、[`Self::nlp`] 等基于 UBUS 调用的方法都是如此。
+    ///
+    /// ```
+    /// # use miai::{Error, Xiaoai};
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> miai::Result<()> {
+    /// # let xiaoai = Xiaoai::load(b"[]".as_slice())?;
+    /// // 忘记了给键加引号
+    /// let result = xiaoai
+    ///     .ubus_call("device_id", "mediaplayer", "player_get_play_status", "{media: 1}")
+    ///     .await;
+    /// assert!(matches!(result, Err(Error::InvalidInput(_))));
+    /// # Ok(())
+    /// # }
+    /// ```
     pub async fn ubus_call(
         &self,
         device_id: &str,
@@ -160,6 +900,12 @@ impl Xiaoai {
         method: &str,
         message: &str,
     ) -> crate::Result<XiaoaiResponse> {
+        if let Err(err) = serde_json::from_str::<serde::de::IgnoredAny>(message) {
+            return Err(crate::Error::InvalidInput(format!(
+                "UBUS 消息不是有效的 JSON: {err}"
+            )));
+        }
+
         let form = HashMap::from([
             ("deviceId", device_id),
             ("method", method),
@@ -167,7 +913,25 @@ impl Xiaoai {
             ("message", message),
         ]);
 
-        self.post("remote/ubus", form).await
+        match self.post("remote/ubus", form).await {
+            Err(err @ crate::Error::Api { .. }) => {
+                Err(self.miio_did_error(device_id).unwrap_or(err))
+            }
+            result => result,
+        }
+    }
+
+    /// `device_id` 是已缓存的设备列表中某个设备的 miio did 时，返回对应的错误。不会发出请求。
+    fn miio_did_error(&self, device_id: &str) -> Option<crate::Error> {
+        let devices = self.device_cache.as_ref()?.peek()?;
+        let info = devices
+            .into_iter()
+            .find(|info| info.miio_did.as_deref() == Some(device_id))?;
+
+        Some(crate::Error::MiioDidGiven {
+            did: device_id.to_string(),
+            device_id: info.device_id,
+        })
     }
 
     /// 请求小爱设备播报文本。
@@ -178,6 +942,69 @@ impl Xiaoai {
             .await
     }
 
+    /// 请求小爱设备分段播报长文本，避免被截断。
+    ///
+    /// 文本会在句末的标点和换行处拆分为不超过 [`TTS_CHUNK_CHARS`] 个字符的片段，
+    /// 过长的句子会被强制拆开。每段播报完毕后才会发送下一段，返回每段的响应。
+    /// 演练模式下无法读取播报状态，不会等待，直接返回每段的演练响应。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use miai::{TTS_CHUNK_CHARS, Xiaoai};
+    /// # use serde_json::Value;
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> miai::Result<()> {
+    /// let xiaoai = Xiaoai::load(b"[]".as_slice())?.with_dry_run(true);
+    /// let long = "啊".repeat(TTS_CHUNK_CHARS + 50);
+    /// let text = format!("你好。{long}。再见！");
+    /// let responses = xiaoai.tts_chunked("device_id", &text).await?;
+    ///
+    /// let chunks: Vec<String> = responses
+    ///     .iter()
+    ///     .map(|response| {
+    ///         let message = response.data["form"]["message"].as_str().unwrap();
+    ///         let message: Value = serde_json::from_str(message).unwrap();
+    ///         message["text"].as_str().unwrap().to_string()
+    ///     })
+    ///     .collect();
+    /// // 过长的句子在第 TTS_CHUNK_CHARS 个字处被强制拆开，剩下的部分与之后的短句合并
+    /// assert_eq!(
+    ///     chunks,
+    ///     [
+    ///         "你好。".to_string(),
+    ///         "啊".repeat(TTS_CHUNK_CHARS),
+    ///         format!("{}。再见！", "啊".repeat(50)),
+    ///     ]
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn tts_chunked(
+        &self,
+        device_id: &str,
+        text: &str,
+    ) -> crate::Result<Vec<XiaoaiResponse>> {
+        // 播报开始前可能有一段延迟，之后按每个字约 0.5 秒估计时长
+        const START_TIMEOUT: Duration = Duration::from_secs(3);
+        const CHAR_DURATION: Duration = Duration::from_millis(500);
+
+        let chunks = split_sentences(text, TTS_CHUNK_CHARS);
+        let mut responses = Vec::with_capacity(chunks.len());
+        for chunk in chunks {
+            responses.push(self.tts(device_id, &chunk).await?);
+            if self.dry_run {
+                continue;
+            }
+            let chars = chunk.chars().count() as u32;
+            let timeout = START_TIMEOUT + CHAR_DURATION * chars;
+            self.wait_playback(device_id, START_TIMEOUT, timeout)
+                .await?;
+        }
+
+        Ok(responses)
+    }
+
     /// 请求小爱播放 `url`。
     pub async fn play_url(&self, device_id: &str, url: &str) -> crate::Result<XiaoaiResponse> {
         let message = json!({
@@ -195,6 +1022,118 @@ impl Xiaoai {
             .await
     }
 
+    /// 请求小爱依次播放多个链接，返回每个链接的结果。
+    ///
+    /// 小爱没有通用的播放队列，因此会在上一首播放结束，或播放超过 `track_timeout` 后再播放下一首，
+    /// 直到开始播放最后一首才返回。演练时不会等待。
+    ///
+    /// 不是 HTTP(S) 的链接，或请求播放失败的链接会被跳过，并以 [`Error::InvalidInput`][crate::Error::InvalidInput]
+    /// 或请求的错误作为其结果，不影响其他链接。成功的个数即被接受的个数。
+    ///
+    /// # Errors
+    ///
+    /// 等待播放结束时查询播放器状态失败，会中止整个列表并返回该错误。
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # async fn play(xiaoai: miai::Xiaoai) -> miai::Result<()> {
+    /// let urls = ["http://music-url/1.mp3", "not a url", "http://music-url/2.mp3"];
+    /// let results = xiaoai
+    ///     .play_urls("device_id", &urls, Duration::from_secs(10 * 60))
+    ///     .await?;
+    /// let accepted = results.iter().filter(|(_, result)| result.is_ok()).count();
+    /// println!("播放了 {accepted}/{} 首", urls.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn play_urls<'a>(
+        &self,
+        device_id: &str,
+        urls: &[&'a str],
+        track_timeout: Duration,
+    ) -> crate::Result<Vec<(&'a str, crate::Result<XiaoaiResponse>)>> {
+        // 链接需要一些时间缓冲才会开始播放
+        const START_TIMEOUT: Duration = Duration::from_secs(10);
+
+        let mut results = Vec::with_capacity(urls.len());
+        let mut playing = false;
+        for &url in urls {
+            let valid = Url::parse(url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"));
+            if !valid {
+                warn!("跳过无效的链接: {url}");
+                let err = crate::Error::InvalidInput(format!("无效的链接 `{url}`"));
+                results.push((url, Err(err)));
+                continue;
+            }
+
+            if playing && !self.dry_run {
+                self.wait_playback(device_id, START_TIMEOUT, track_timeout)
+                    .await?;
+            }
+            let result = self.play_url(device_id, url).await;
+            match &result {
+                Ok(_) => playing = true,
+                Err(err) => warn!("跳过播放失败的链接 {url}: {err}"),
+            }
+            results.push((url, result));
+        }
+
+        Ok(results)
+    }
+
+    /// 请求小爱播放本地文件。
+    ///
+    /// 会在本机临时启动一个 HTTP 服务提供该文件，再让小爱通过 [`Self::play_url`] 播放，
+    /// 直到播放结束或超过 `timeout` 后才会关闭服务并返回。返回的是 [`Self::play_url`] 的响应。
+    ///
+    /// 小爱需要能访问本机，即两者处于同一局域网，且防火墙允许传入的 TCP 连接。
+    /// 服务会监听一个随机端口，链接的主机是本机通往外网的那张网卡的地址。
+    /// 小爱在短时间内没有开始播放时（比如无法访问本机），视为播放结束并立即返回。
+    pub async fn play_file(
+        &self,
+        device_id: &str,
+        path: impl AsRef<Path>,
+        timeout: Duration,
+    ) -> crate::Result<XiaoaiResponse> {
+        // 小爱需要先下载一部分文件才会开始播放
+        const START_TIMEOUT: Duration = Duration::from_secs(10);
+
+        let server = FileServer::serve(path).await?;
+        let response = self.play_url(device_id, server.url().as_str()).await?;
+        self.wait_playback(device_id, START_TIMEOUT, timeout)
+            .await?;
+        drop(server);
+
+        Ok(response)
+    }
+
+    /// 等待播放开始，再等待播放结束，最多等待 `timeout`。
+    ///
+    /// 超过 `start_timeout` 仍未开始播放时，视为已经播放完毕，避免错过很短的播放。
+    async fn wait_playback(
+        &self,
+        device_id: &str,
+        start_timeout: Duration,
+        timeout: Duration,
+    ) -> crate::Result<()> {
+        const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+        let start = Instant::now();
+        let mut started = false;
+        while start.elapsed() < timeout {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            match self.player_status(device_id).await?.state {
+                state if state.is_active() => started = true,
+                _ if started || start.elapsed() >= start_timeout => break,
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
     /// 请求小爱播放音乐。
     ///
     /// 和 [`Self::play_url`] 相比，此方法针对音频特化，能支持更多参数，但并非所有机型都支持。
@@ -239,8 +1178,41 @@ impl Xiaoai {
             .await
     }
 
-    /// 请求小爱调整音量。
+    /// 请求小爱调整音量，范围为 0 到 [`MAX_VOLUME`]。
+    ///
+    /// # Errors
+    ///
+    /// 音量超出范围时不会发出请求，直接返回 [`Error::InvalidVolume`][crate::Error::InvalidVolume]。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use miai::{Error, Xiaoai};
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> miai::Result<()> {
+    /// # let xiaoai = Xiaoai::load(b"[]".as_slice())?;
+    /// let result = xiaoai.set_volume("device_id", 101).await;
+    /// assert!(matches!(result, Err(Error::InvalidVolume(101))));
+    ///
+    /// // 演练模式下同样不会获取设备列表
+    /// let dry_run = xiaoai.with_dry_run(true);
+    /// let response = dry_run.set_volume("device_id", 30).await?;
+    /// assert_eq!(response.data["form"]["method"], "player_set_volume");
+    ///
+    /// // 范围两端的音量都可以设置
+    /// for volume in [0, 100] {
+    ///     let response = dry_run.set_volume("device_id", volume).await?;
+    ///     let message = response.data["form"]["message"].as_str().unwrap();
+    ///     let message: serde_json::Value = serde_json::from_str(message).unwrap();
+    ///     assert_eq!(message["volume"], volume);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
     pub async fn set_volume(&self, device_id: &str, volume: u32) -> crate::Result<XiaoaiResponse> {
+        if volume > MAX_VOLUME {
+            return Err(crate::Error::InvalidVolume(volume));
+        }
         let message = json!({
             "volume": volume,
             "media": "app_ios"
@@ -251,12 +1223,203 @@ impl Xiaoai {
             .await
     }
 
+    /// 设置小爱的音量，并读回音量确认已经生效。
+    ///
+    /// 设备正在播报时，设置音量有时会被忽略。设置后会在短时间内多次读取音量，
+    /// 一致时返回设置的响应，适合在接下来的操作依赖音量时使用。
+    ///
+    /// # Errors
+    ///
+    /// 多次读取后音量仍不一致时，返回 [`Error::VolumeNotApplied`][crate::Error::VolumeNotApplied]。
+    /// 音量超出范围时同 [`Self::set_volume`]。
+    pub async fn set_volume_verified(
+        &self,
+        device_id: &str,
+        volume: u32,
+    ) -> crate::Result<XiaoaiResponse> {
+        const ATTEMPTS: u32 = 5;
+        const INTERVAL: Duration = Duration::from_millis(500);
+
+        let response = self.set_volume(device_id, volume).await?;
+        let mut actual = self.get_volume(device_id).await?;
+        for _ in 1..ATTEMPTS {
+            if actual == volume {
+                break;
+            }
+            tokio::time::sleep(INTERVAL).await;
+            actual = self.get_volume(device_id).await?;
+        }
+        if actual != volume {
+            return Err(crate::Error::VolumeNotApplied {
+                expected: volume,
+                actual,
+            });
+        }
+
+        Ok(response)
+    }
+
+    /// 获取设备内部音量的最大值，参见 [`DeviceModel::volume_scale`]，未知的机型视为 [`MAX_VOLUME`]。
+    ///
+    /// 会获取设备列表以确定机型，启用 [`XiaoaiBuilder::device_cache`] 时使用缓存的列表。
+    /// 演练模式下不会获取，直接返回 [`MAX_VOLUME`]。
+    ///
+    /// # Errors
+    ///
+    /// 找不到设备时返回 [`Error::DeviceNotFound`][crate::Error::DeviceNotFound]。
+    pub async fn max_volume(&self, device_id: &str) -> crate::Result<u32> {
+        if self.dry_run {
+            return Ok(MAX_VOLUME);
+        }
+        let info = self.device_info_one(device_id).await?;
+
+        Ok(info.model().map_or(MAX_VOLUME, |model| model.volume_scale))
+    }
+
+    /// 获取小爱当前的音量，范围为 0 到 100。
+    pub async fn get_volume(&self, device_id: &str) -> crate::Result<u32> {
+        Ok(self.player_status(device_id).await?.volume.into())
+    }
+
+    /// 相对地调整小爱的音量，返回调整后的音量。
+    ///
+    /// 会先读取当前音量，加上 `delta` 并限制在 0 到 100 之间，再设置音量。
+    pub async fn adjust_volume(&self, device_id: &str, delta: i32) -> crate::Result<u32> {
+        let current = self.get_volume(device_id).await?;
+        let volume = current.saturating_add_signed(delta).min(MAX_VOLUME);
+        if volume != current {
+            self.set_volume(device_id, volume).await?;
+        }
+
+        Ok(volume)
+    }
+
+    /// 在 `over` 时间内把音量逐步调整到 `target`，返回调整后的音量。
+    ///
+    /// 会先读取当前音量，再分 `steps` 步均匀地调大或调小，每步之间等待 `over / steps`，
+    /// 最后一步正好达到 `target`。步数不会超过音量的差值，因此相差很小时请求也很少，
+    /// 一般使用 [`DEFAULT_RAMP_STEPS`] 即可。当前音量已经是 `target` 时不会发出设置请求。
+    ///
+    /// # Errors
+    ///
+    /// 音量超出范围时不会发出请求，直接返回 [`Error::InvalidVolume`][crate::Error::InvalidVolume]。
+    pub async fn ramp_volume(
+        &self,
+        device_id: &str,
+        target: u32,
+        over: Duration,
+        steps: u32,
+    ) -> crate::Result<u32> {
+        if target > MAX_VOLUME {
+            return Err(crate::Error::InvalidVolume(target));
+        }
+        let current = self.get_volume(device_id).await?;
+        if current == target {
+            return Ok(target);
+        }
+        let diff = i64::from(target) - i64::from(current);
+        let steps = steps.clamp(1, diff.unsigned_abs() as u32);
+        let interval = over / steps;
+        for step in 1..=steps {
+            if step > 1 {
+                tokio::time::sleep(interval).await;
+            }
+            let volume = i64::from(current) + diff * i64::from(step) / i64::from(steps);
+            let volume = volume.clamp(0, MAX_VOLUME.into()) as u32;
+            self.set_volume(device_id, volume).await?;
+        }
+
+        Ok(target)
+    }
+
+    /// 将小爱静音，返回静音前的音量。
+    ///
+    /// 静音前的音量会被记录下来，供 [`Self::unmute`] 恢复，记录在所有克隆的 `Xiaoai` 间共享。
+    /// 如果已经静音，则不会覆盖之前的记录。
+    ///
+    /// # Panics
+    ///
+    /// 当内部发生锁中毒时会 panic。
+    pub async fn mute(&self, device_id: &str) -> crate::Result<u32> {
+        let volume = self.get_volume(device_id).await?;
+        if volume != 0 {
+            self.set_volume(device_id, 0).await?;
+            self.muted_volumes
+                .lock()
+                .unwrap()
+                .insert(device_id.to_string(), volume);
+        }
+
+        Ok(volume)
+    }
+
+    /// 取消静音，返回恢复后的音量。
+    ///
+    /// 会恢复到 [`Self::mute`] 前的音量。如果没有记录（比如是在另一个进程中静音的），
+    /// 且当前音量为 0，则恢复到 [`DEFAULT_UNMUTE_VOLUME`]；当前音量不为 0 时什么也不做。
+    ///
+    /// # Panics
+    ///
+    /// 当内部发生锁中毒时会 panic。
+    pub async fn unmute(&self, device_id: &str) -> crate::Result<u32> {
+        let muted_volume = self.muted_volumes.lock().unwrap().remove(device_id);
+        let volume = match muted_volume {
+            Some(volume) => volume,
+            None => match self.get_volume(device_id).await? {
+                0 => DEFAULT_UNMUTE_VOLUME,
+                volume => return Ok(volume),
+            },
+        };
+        self.set_volume(device_id, volume).await?;
+
+        Ok(volume)
+    }
+
     /// 请求小爱执行文本。
     ///
-    /// 效果和口头询问一样。
+    /// 效果和口头询问一样。需要识别结果时可以使用 [`Self::nlp_parsed`]。
     pub async fn nlp(&self, device_id: &str, text: &str) -> crate::Result<XiaoaiResponse> {
+        self.ai_service(device_id, text, true).await
+    }
+
+    /// 请求小爱静默地执行文本，比如“打开卧室的灯”，执行后小爱不会播报回答。
+    ///
+    /// 同 [`Self::nlp`]，但关闭了语音回复。指令照常执行，对话记录中通常也会出现这次的提问和应答，
+    /// 可以尝试通过 [`Self::conversations`] 查看，但服务端并不保证，有的机型上可能没有记录。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use miai::Xiaoai;
+    /// # use serde_json::Value;
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> miai::Result<()> {
+    /// let xiaoai = Xiaoai::load(b"[]".as_slice())?.with_dry_run(true);
+    /// let response = xiaoai.execute_silent("device_id", "打开卧室的灯").await?;
+    ///
+    /// let message = response.data["form"]["message"].as_str().unwrap();
+    /// let message: Value = serde_json::from_str(message)?;
+    /// assert_eq!(message["tts"], 0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn execute_silent(
+        &self,
+        device_id: &str,
+        text: &str,
+    ) -> crate::Result<XiaoaiResponse> {
+        self.ai_service(device_id, text, false).await
+    }
+
+    /// 请求小爱执行文本，`tts` 决定是否播报回答。
+    async fn ai_service(
+        &self,
+        device_id: &str,
+        text: &str,
+        tts: bool,
+    ) -> crate::Result<XiaoaiResponse> {
         let message = json!({
-            "tts": 1,
+            "tts": u8::from(tts),
             "nlp": 1,
             "nlp_text": text
         })
@@ -266,10 +1429,130 @@ impl Xiaoai {
             .await
     }
 
+    /// 同 [`Self::nlp`]，但解析出意图、槽位和答复，参见 [`NlpResult`]。
+    ///
+    /// 响应的结构无法识别时不会报错，而是返回各字段为空的结果，原始数据仍保留在 [`NlpResult::raw`] 中。
+    pub async fn nlp_parsed(&self, device_id: &str, text: &str) -> crate::Result<NlpResult> {
+        Ok(self.nlp(device_id, text).await?.into())
+    }
+
+    /// 请求小爱执行文本，并等待小爱的回答。
+    ///
+    /// 执行 [`Self::nlp`] 后轮询对话记录，直到出现同一请求的记录且已有应答。优先通过
+    /// [`XiaoaiResponse::request_id`] 匹配，响应中没有 ID 时，匹配执行后出现的、提问与 `text` 相同的记录。
+    /// 记录需要一段时间才会出现在对话记录中，`timeout` 不宜太短。
+    ///
+    /// # Errors
+    ///
+    /// 超过 `timeout` 仍未等到回答时，返回 [`Error::Timeout`][crate::Error::Timeout]。
+    pub async fn ask_and_wait(
+        &self,
+        device_id: &str,
+        text: &str,
+        timeout: Duration,
+    ) -> crate::Result<conversation::Record> {
+        self.nlp_and_wait(device_id, text, timeout)
+            .await?
+            .ok_or(crate::Error::Timeout)
+    }
+
+    /// 让小爱搜索并播放音乐，如“周杰伦 晴天”。
+    ///
+    /// 通过 [`Self::nlp`] 执行“播放`query`”，由设备自带的音乐服务搜索和播放，结果取决于账号绑定的音乐会员，
+    /// 没有版权时小爱可能只会回复无法播放。之后会短暂等待对话记录，返回小爱的应答，其中通常有类型为
+    /// MUSIC 的应答，即实际播放的歌曲，参见 [`AnswerPayload::Music`][conversation::AnswerPayload::Music]。
+    /// 等不到对话记录时返回 `None`，此时请求已经发出，音乐可能仍会播放。
+    ///
+    /// # Errors
+    ///
+    /// `query` 为空时，返回 [`Error::InvalidInput`][crate::Error::InvalidInput]。
+    pub async fn play_music_search(
+        &self,
+        device_id: &str,
+        query: &str,
+    ) -> crate::Result<Option<conversation::Record>> {
+        const TIMEOUT: Duration = Duration::from_secs(10);
+
+        let query = query.trim();
+        if query.is_empty() {
+            return Err(crate::Error::InvalidInput("搜索的内容不能为空".into()));
+        }
+
+        self.nlp_and_wait(device_id, &format!("播放{query}"), TIMEOUT)
+            .await
+    }
+
+    /// 执行 [`Self::nlp`] 后轮询对话记录，超时返回 `None`，参见 [`Self::ask_and_wait`]。
+    async fn nlp_and_wait(
+        &self,
+        device_id: &str,
+        text: &str,
+        timeout: Duration,
+    ) -> crate::Result<Option<conversation::Record>> {
+        const POLL_INTERVAL: Duration = Duration::from_secs(1);
+        const POLL_LIMIT: u32 = 5;
+
+        let hardware = self.hardware(device_id).await?;
+        // 服务端和本机的时钟可能有偏差，稍微放宽
+        let sent_at = OffsetDateTime::now_utc() - Duration::from_secs(5);
+        let request_id = self.nlp(device_id, text).await?.request_id();
+
+        let start = Instant::now();
+        while start.elapsed() < timeout {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let records = self
+                .conversations(device_id, &hardware, OffsetDateTime::now_utc(), POLL_LIMIT)
+                .await?
+                .records;
+            let record = records.into_iter().find(|record| match &request_id {
+                Some(request_id) => record.request_id == *request_id,
+                None => record.time >= sent_at && record.query == text,
+            });
+            // 记录可能先于应答出现
+            if let Some(record) = record
+                && !record.answers.is_empty()
+            {
+                return Ok(Some(record));
+            }
+        }
+
+        Ok(None)
+    }
+
     /// 获取播放器的状态信息。
     ///
+    /// # Errors
+    ///
+    /// 当响应中找不到播放状态或音量时，返回 [`Error::UnexpectedResponse`][crate::Error::UnexpectedResponse]。
+    pub async fn player_status(&self, device_id: &str) -> crate::Result<PlayerStatus> {
+        let response = self.raw_player_status(device_id).await?;
+        trace!("获取到播放器状态: {}", response.data);
+
+        PlayerStatus::from_data(&response.data)
+            .ok_or(crate::Error::UnexpectedResponse(response.data))
+    }
+
+    /// 检查登录状态是否有效、设备是否可达，没有副作用。
+    ///
+    /// 只读取播放器状态而不解析，适合用于健康检查。
+    ///
+    /// # Errors
+    ///
+    /// 失败的原因可以通过错误区分：
+    ///
+    /// - 登录状态失效且无法刷新时，返回 [`Error::SessionExpired`][crate::Error::SessionExpired]；
+    /// - 设备离线时，返回的错误满足 [`Error::is_device_offline`][crate::Error::is_device_offline]；
+    /// - 网络不可用时，返回 [`Error::Reqwest`][crate::Error::Reqwest] 或 [`Error::Timeout`][crate::Error::Timeout]。
+    pub async fn ping(&self, device_id: &str) -> crate::Result<()> {
+        self.raw_player_status(device_id).await?;
+
+        Ok(())
+    }
+
+    /// 同 [`Self::player_status`]，但返回原始的响应。
+    ///
     /// 可能包含播放状态，音量和循环播放设置。
-    pub async fn player_status(&self, device_id: &str) -> crate::Result<XiaoaiResponse> {
+    pub async fn raw_player_status(&self, device_id: &str) -> crate::Result<XiaoaiResponse> {
         let message = json!({"media": "app_ios"}).to_string();
 
         self.ubus_call(device_id, "mediaplayer", "player_get_play_status", &message)
@@ -277,6 +1560,8 @@ impl Xiaoai {
     }
 
     /// 设置播放器的播放状态。
+    ///
+    /// 返回原始的响应，即使设备没有接受操作也不会报错，需要确认时请使用 [`Self::set_play_state_checked`]。
     pub async fn set_play_state(
         &self,
         device_id: &str,
@@ -287,6 +1572,8 @@ impl Xiaoai {
             PlayState::Pause => "pause",
             PlayState::Stop => "stop",
             PlayState::Toggle => "toggle",
+            PlayState::Next => "next",
+            PlayState::Previous => "prev",
         };
         let message = json!({"action": action, "media": "app_ios"}).to_string();
 
@@ -294,9 +1581,427 @@ impl Xiaoai {
             .await
     }
 
+    /// 同 [`Self::set_play_state`]，但会检查操作是否被接受。
+    ///
+    /// # Errors
+    ///
+    /// 响应的 `code` 或设备上 UBUS 调用的 `code` 不为 0 时，返回带有服务端消息的
+    /// [`Error::Api`][crate::Error::Api]，参见 [`UbusResponse::parse`][crate::UbusResponse::parse]。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use miai::{PlayState, Xiaoai};
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> miai::Result<()> {
+    /// let xiaoai = Xiaoai::load(b"[]".as_slice())?.with_dry_run(true);
+    /// xiaoai.set_play_state_checked("device_id", PlayState::Pause).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn set_play_state_checked(
+        &self,
+        device_id: &str,
+        state: PlayState,
+    ) -> crate::Result<()> {
+        let response = self
+            .set_play_state(device_id, state)
+            .await?
+            .error_for_code()?;
+        crate::UbusResponse::from(response).parse::<serde::de::IgnoredAny>()?;
+
+        Ok(())
+    }
+
+    /// 停止正在进行的播报。
+    ///
+    /// 播报与播放 [`Xiaoai::play_url`] 等媒体不在同一通道，`app_ios` 媒体的
+    /// [`PlayState::Stop`] 有时无法打断播报，需要对 `common` 媒体发送停止操作。
+    pub async fn stop_tts(&self, device_id: &str) -> crate::Result<XiaoaiResponse> {
+        let message = json!({"action": "stop", "media": "common"}).to_string();
+
+        self.ubus_call(device_id, "mediaplayer", "player_play_operation", &message)
+            .await
+    }
+
+    /// 停止设备正在发出的任何声音，无论是播报还是播放。
+    ///
+    /// 依次停止媒体播放和播报，返回最后一个响应。
+    pub async fn stop_all(&self, device_id: &str) -> crate::Result<XiaoaiResponse> {
+        self.set_play_state(device_id, PlayState::Stop).await?;
+
+        self.stop_tts(device_id).await
+    }
+
+    /// 设置播放器的循环播放模式。
+    ///
+    /// 设置后会在短时间内多次读回播放器状态进行确认，与 [`Self::set_volume_verified`] 类似。
+    /// 设置的是 `media` 为 `common` 的播放器，读回的则是 [`Self::player_status`] 中 `app_ios`
+    /// 的状态，两者在已知的机型上是一致的。
+    ///
+    /// # Errors
+    ///
+    /// 设备不支持该模式，即多次读回的模式仍与设置的不一致时，返回 [`Error::Unsupported`][crate::Error::Unsupported]。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "test-util")]
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() -> miai::Result<()> {
+    /// # use miai::{Error, MockTransport, PlayMode, Xiaoai};
+    /// # use reqwest::Method;
+    /// # use serde_json::{Value, json};
+    /// let mock = MockTransport::new();
+    /// let xiaoai = Xiaoai::builder()
+    ///     .transport(mock.clone())
+    ///     .load(b"[]".as_slice())?;
+    /// let modes = [
+    ///     (PlayMode::RepeatOne, 0),
+    ///     (PlayMode::RepeatAll, 1),
+    ///     (PlayMode::Shuffle, 3),
+    ///     (PlayMode::Sequence, 4),
+    /// ];
+    /// // 设置和读回都是 UBUS 调用，依次返回设置的响应和读回的播放器状态。
+    /// // 最后再读回一次列表循环，之后会一直重复，用于下面不支持的情况
+    /// for loop_type in modes.map(|(_, loop_type)| loop_type).into_iter().chain([1]) {
+    ///     let info = json!({"status": 1, "volume": 30, "loop_type": loop_type});
+    ///     mock.respond(Method::POST, "remote/ubus", json!({}))
+    ///         .respond(Method::POST, "remote/ubus", json!({"info": info.to_string()}));
+    /// }
+    ///
+    /// for (mode, _) in modes {
+    ///     xiaoai.set_play_mode("device_id", mode).await?;
+    /// }
+    ///
+    /// let requests = mock.requests();
+    /// assert_eq!(requests.len(), 8);
+    /// let (set, get) = (requests[0].form(), requests[1].form());
+    /// assert_eq!(set["method"], "player_set_loop");
+    /// assert_eq!(get["method"], "player_get_play_status");
+    /// let media = |form: &std::collections::HashMap<String, String>| {
+    ///     serde_json::from_str::<Value>(&form["message"]).unwrap()["media"].clone()
+    /// };
+    /// assert_eq!((media(&set), media(&get)), (json!("common"), json!("app_ios")));
+    ///
+    /// // 多次读回仍不一致时视为不支持
+    /// let result = xiaoai.set_play_mode("device_id", PlayMode::Shuffle).await;
+    /// assert!(matches!(result, Err(Error::Unsupported(_))));
+    /// // 设置一次，读回五次
+    /// assert_eq!(mock.requests().len(), 8 + 1 + 5);
+    /// # Ok(())
+    /// # }
+    /// # #[cfg(not(feature = "test-util"))]
+    /// # fn main() {}
+    /// ```
+    pub async fn set_play_mode(
+        &self,
+        device_id: &str,
+        mode: PlayMode,
+    ) -> crate::Result<XiaoaiResponse> {
+        const ATTEMPTS: u32 = 5;
+        const INTERVAL: Duration = Duration::from_millis(500);
+
+        let message = json!({"type": mode.loop_type(), "media": "common"}).to_string();
+        let response = self
+            .ubus_call(device_id, "mediaplayer", "player_set_loop", &message)
+            .await?;
+
+        // 设置后播放器状态不一定立即更新
+        let mut actual = self.play_mode(device_id).await?;
+        for _ in 1..ATTEMPTS {
+            if actual == mode {
+                break;
+            }
+            tokio::time::sleep(INTERVAL).await;
+            actual = self.play_mode(device_id).await?;
+        }
+        if actual != mode {
+            return Err(crate::Error::Unsupported(format!("播放模式 `{mode}`")));
+        }
+
+        Ok(response)
+    }
+
+    /// 获取播放器的循环播放模式。
+    ///
+    /// # Errors
+    ///
+    /// 当响应中找不到循环播放模式时，返回 [`Error::UnexpectedResponse`][crate::Error::UnexpectedResponse]。
+    pub async fn play_mode(&self, device_id: &str) -> crate::Result<PlayMode> {
+        let response = self.raw_player_status(device_id).await?;
+
+        PlayerStatus::from_data(&response.data)
+            .and_then(|status| status.play_mode)
+            .ok_or(crate::Error::UnexpectedResponse(response.data))
+    }
+
+    /// 播放下一首。
+    ///
+    /// # Errors
+    ///
+    /// 当前没有在播放时，返回 [`Error::NotPlaying`][crate::Error::NotPlaying]。
+    pub async fn next_track(&self, device_id: &str) -> crate::Result<XiaoaiResponse> {
+        self.switch_track(device_id, PlayState::Next).await
+    }
+
+    /// 播放上一首。
+    ///
+    /// # Errors
+    ///
+    /// 当前没有在播放时，返回 [`Error::NotPlaying`][crate::Error::NotPlaying]。
+    pub async fn prev_track(&self, device_id: &str) -> crate::Result<XiaoaiResponse> {
+        self.switch_track(device_id, PlayState::Previous).await
+    }
+
+    async fn switch_track(
+        &self,
+        device_id: &str,
+        state: PlayState,
+    ) -> crate::Result<XiaoaiResponse> {
+        // 停止时切换曲目没有意义，部分机型还会返回奇怪的错误。演练时无法读取状态，跳过检查
+        if !self.dry_run
+            && let PlaybackState::Stopped = self.player_status(device_id).await?.state
+        {
+            return Err(crate::Error::NotPlaying);
+        }
+
+        self.set_play_state(device_id, state).await
+    }
+
+    /// 设置定时停止播放。
+    ///
+    /// 定时器由设备自身维护，即使程序退出也会生效。返回预计停止播放的时间，
+    /// 偏移参见 [`XiaoaiBuilder::utc_offset`]。重复设置会覆盖之前的定时器。
+    pub async fn set_sleep_timer(
+        &self,
+        device_id: &str,
+        duration: Duration,
+    ) -> crate::Result<OffsetDateTime> {
+        let message = json!({
+            "action": "pause_later",
+            "second": duration.as_secs(),
+            "media": "app_ios"
+        })
+        .to_string();
+        self.ubus_call(
+            device_id,
+            "mediaplayer",
+            "player_set_shutdown_timer",
+            &message,
+        )
+        .await?;
+
+        Ok((OffsetDateTime::now_utc() + duration).to_offset(self.utc_offset))
+    }
+
+    /// 取消定时停止播放。
+    pub async fn cancel_sleep_timer(&self, device_id: &str) -> crate::Result<XiaoaiResponse> {
+        let message = json!({"action": "cancel_ending", "media": "app_ios"}).to_string();
+
+        self.ubus_call(
+            device_id,
+            "mediaplayer",
+            "player_set_shutdown_timer",
+            &message,
+        )
+        .await
+    }
+
+    /// 列出设备上的所有闹钟。
+    pub async fn list_alarms(&self, device_id: &str) -> crate::Result<Vec<Alarm>> {
+        let response = self
+            .ubus_call(device_id, "alarm", "get_alarm_list", "{}")
+            .await?;
+        trace!("获取到闹钟列表: {}", response.data);
+        let info = ubus_info(&response.data);
+        // 列表可能直接作为 `info`，也可能包在 `alarms` 里
+        let alarms = match info {
+            Value::Object(mut info) => info.remove("alarms").unwrap_or_default(),
+            info => info,
+        };
+
+        match alarms {
+            Value::Null => Ok(Vec::new()),
+            alarms => Ok(serde_json::from_value(alarms)?),
+        }
+    }
+
+    /// 在设备上创建闹钟，`alarm` 的 [`id`][Alarm::id] 会被忽略。
+    pub async fn create_alarm(
+        &self,
+        device_id: &str,
+        alarm: &Alarm,
+    ) -> crate::Result<XiaoaiResponse> {
+        let alarm = Alarm {
+            id: None,
+            ..alarm.clone()
+        };
+        let message = serde_json::to_string(&alarm)?;
+
+        self.ubus_call(device_id, "alarm", "add_alarm", &message)
+            .await
+    }
+
+    /// 删除设备上的闹钟。
+    pub async fn delete_alarm(
+        &self,
+        device_id: &str,
+        alarm_id: &str,
+    ) -> crate::Result<XiaoaiResponse> {
+        let message = json!({"id": alarm_id}).to_string();
+
+        self.ubus_call(device_id, "alarm", "delete_alarm", &message)
+            .await
+    }
+
+    /// 设置设备的勿扰模式。
+    ///
+    /// `start` 和 `end` 是设备本地时区的时间，需要同时指定；都不指定时为全天勿扰。
+    /// 关闭勿扰模式时，时间同样会被写入。参见 [`Dnd`]。
+    ///
+    /// # Errors
+    ///
+    /// 只指定了 `start` 或 `end` 其中之一时，返回 [`Error::InvalidInput`][crate::Error::InvalidInput]。
+    pub async fn set_dnd(
+        &self,
+        device_id: &str,
+        enabled: bool,
+        start: Option<Time>,
+        end: Option<Time>,
+    ) -> crate::Result<XiaoaiResponse> {
+        let (start, end) = match (start, end) {
+            (Some(start), Some(end)) => (start, end),
+            (None, None) => (Time::MIDNIGHT, Time::MIDNIGHT),
+            _ => {
+                return Err(crate::Error::InvalidInput(
+                    "勿扰模式需要同时指定开始和结束时间".into(),
+                ));
+            }
+        };
+        let message = serde_json::to_string(&Dnd {
+            enabled,
+            start,
+            end,
+        })?;
+
+        self.ubus_call(device_id, "system", "set_dnd", &message)
+            .await
+    }
+
+    /// 获取设备的勿扰模式设置。
+    pub async fn get_dnd(&self, device_id: &str) -> crate::Result<Dnd> {
+        let response = self.ubus_call(device_id, "system", "get_dnd", "{}").await?;
+        trace!("获取到勿扰模式: {}", response.data);
+
+        Ok(serde_json::from_value(ubus_info(&response.data))?)
+    }
+
+    /// 读取设备的 MIoT 属性，如何查找 `siid` 和 `piid` 参见 [`miot`][crate::miot]。
+    ///
+    /// 返回的 [`Property`] 带有 MIoT 状态码，读取失败时不会报错，需要自行检查。
+    /// 需要读取多个属性时，使用 [`Self::get_properties`] 可以减少请求次数。
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use miai::Xiaoai;
+    /// # async fn volume(xiaoai: Xiaoai) -> miai::Result<()> {
+    /// // LX06 的音量
+    /// let property = xiaoai.get_property("device_id", 2, 1).await?;
+    /// if property.is_ok() {
+    ///     println!("{:?}", property.value);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_property(
+        &self,
+        device_id: &str,
+        siid: u32,
+        piid: u32,
+    ) -> crate::Result<Property> {
+        let mut properties = self.get_properties(device_id, &[(siid, piid)]).await?;
+
+        Ok(properties.remove(0))
+    }
+
+    /// 在一次请求中读取设备的多个 MIoT 属性，`ids` 为 `(siid, piid)` 的列表。
+    ///
+    /// 结果与 `ids` 一一对应，每个属性有各自的状态码，部分属性读取失败不影响其他属性。
+    /// 服务端没有返回某个属性的结果时，其状态码为 -1。
+    pub async fn get_properties(
+        &self,
+        device_id: &str,
+        ids: &[(u32, u32)],
+    ) -> crate::Result<Vec<Property>> {
+        let params: Vec<_> = ids
+            .iter()
+            .map(|(siid, piid)| json!({"siid": siid, "piid": piid}))
+            .collect();
+        let message = json!({ "params": params }).to_string();
+        let mut results = self
+            .miot_call(device_id, "get_properties", &message)
+            .await?;
+
+        // 按请求的顺序整理结果
+        let properties = ids
+            .iter()
+            .map(|&(siid, piid)| {
+                let position = results
+                    .iter()
+                    .position(|result| result.siid == siid && result.piid == piid);
+                match position {
+                    Some(position) => results.remove(position),
+                    None => Property::missing(siid, piid),
+                }
+            })
+            .collect();
+
+        Ok(properties)
+    }
+
+    /// 设置设备的 MIoT 属性，参见 [`Self::get_property`]。
+    pub async fn set_property(
+        &self,
+        device_id: &str,
+        siid: u32,
+        piid: u32,
+        value: impl Into<Value>,
+    ) -> crate::Result<Property> {
+        let message = json!({
+            "params": [{"siid": siid, "piid": piid, "value": value.into()}]
+        })
+        .to_string();
+        let results = self
+            .miot_call(device_id, "set_properties", &message)
+            .await?;
+
+        Ok(results
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| Property::missing(siid, piid)))
+    }
+
+    async fn miot_call(
+        &self,
+        device_id: &str,
+        method: &str,
+        message: &str,
+    ) -> crate::Result<Vec<Property>> {
+        let response = self.ubus_call(device_id, "miot", method, message).await?;
+        trace!("MIoT 属性: {}", response.data);
+
+        miot::parse_properties(ubus_info(&response.data))
+    }
+
     /// 获取小爱的对话记录。
     ///
     /// 会获取直到 `until` 前最多 `limit` 条记录，请注意 `device_id` 要和 `hardware` 相匹配。
+    /// 如果还有更早的记录，可以将返回的 [`next_end_time`][conversation::Data::next_end_time]
+    /// 作为 `until` 获取下一页，或者直接使用 [`Self::conversation_stream`]。
+    ///
+    /// 返回的时间已换算到 [`XiaoaiBuilder::utc_offset`] 设置的偏移，原始的 UTC 时间见 [`Self::raw_conversations`]。
     ///
     /// # Panics
     ///
@@ -313,11 +2018,235 @@ impl Xiaoai {
             .raw_conversations(device_id, hardware, until, limit)
             .await?
             .extract_data()?;
-        let data = serde_json::from_str(&data_string)?;
+        let mut data: conversation::Data = serde_json::from_str(&data_string)?;
+        if !data.skipped.is_empty() {
+            warn!("跳过了 {} 条无法解析的对话记录", data.skipped.len());
+        }
+        data.records = data
+            .records
+            .into_iter()
+            .map(|record| record.to_offset(self.utc_offset))
+            .collect();
+        data.next_end_time = data
+            .next_end_time
+            .map(|time| time.to_offset(self.utc_offset));
 
         Ok(data)
     }
 
+    /// 同 [`Self::conversations`]，但自动查找设备的机型。
+    ///
+    /// 会先获取设备列表，启用了 [`XiaoaiBuilder::device_cache`] 时通常无需额外的请求。
+    /// 已经知道机型时，请直接使用 [`Self::conversations`]。
+    ///
+    /// # Errors
+    ///
+    /// 找不到设备时返回 [`Error::DeviceNotFound`][crate::Error::DeviceNotFound]。
+    pub async fn conversations_auto(
+        &self,
+        device_id: &str,
+        until: OffsetDateTime,
+        limit: u32,
+    ) -> crate::Result<conversation::Data> {
+        let hardware = self.hardware(device_id).await?;
+
+        self.conversations(device_id, &hardware, until, limit).await
+    }
+
+    /// 查找设备的机型，对话记录的接口需要它。
+    async fn hardware(&self, device_id: &str) -> crate::Result<String> {
+        Ok(self.device_info_one(device_id).await?.hardware)
+    }
+
+    /// 获取 `until` 之前的所有对话记录，从新到旧排列。
+    ///
+    /// 每次向服务器请求 `page_size` 条记录，并自动翻页，直到没有更早的记录为止。
+    /// 可以结合 [`StreamExt::take`][futures_util::StreamExt::take] 限制总条数。
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use miai::{Xiaoai, time::OffsetDateTime};
+    /// use futures_util::{StreamExt, TryStreamExt};
+    ///
+    /// # async fn history(xiaoai: Xiaoai) -> miai::Result<()> {
+    /// let records: Vec<_> = xiaoai
+    ///     .conversation_stream("device_id", "hardware", OffsetDateTime::now_utc(), 20)
+    ///     .take(500)
+    ///     .try_collect()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn conversation_stream<'a>(
+        &'a self,
+        device_id: &'a str,
+        hardware: &'a str,
+        until: OffsetDateTime,
+        page_size: u32,
+    ) -> impl Stream<Item = crate::Result<conversation::Record>> + 'a {
+        stream::try_unfold(Some(until), move |cursor| async move {
+            let Some(until) = cursor else {
+                return Ok::<_, crate::Error>(None);
+            };
+            let data = self
+                .conversations(device_id, hardware, until, page_size)
+                .await?;
+            // 游标没有前进时也视为到底了，避免死循环
+            let next = data
+                .next_end_time
+                .filter(|next| !data.records.is_empty() && *next < until);
+            let records = stream::iter(data.records.into_iter().map(Ok));
+
+            Ok(Some((records, next)))
+        })
+        .try_flatten()
+    }
+
+    /// 获取时间在 `start` 和 `end` 之间的对话记录，从新到旧排列。
+    ///
+    /// 包含 `start`，不包含 `end`，即 `start <= time < end`。从 `end` 开始往前翻页，
+    /// 遇到早于 `start` 的记录后就不再请求，每页 `page_size` 条，参见 [`Self::conversation_stream`]。
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use miai::{Xiaoai, time::{Duration, OffsetDateTime}};
+    /// use futures_util::TryStreamExt;
+    ///
+    /// # async fn history(xiaoai: Xiaoai) -> miai::Result<()> {
+    /// // 最近一天的对话记录
+    /// let end = OffsetDateTime::now_utc();
+    /// let records: Vec<_> = xiaoai
+    ///     .conversations_between("device_id", "hardware", end - Duration::DAY, end, 50)
+    ///     .try_collect()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn conversations_between<'a>(
+        &'a self,
+        device_id: &'a str,
+        hardware: &'a str,
+        start: OffsetDateTime,
+        end: OffsetDateTime,
+        page_size: u32,
+    ) -> impl Stream<Item = crate::Result<conversation::Record>> + 'a {
+        self.conversation_stream(device_id, hardware, end, page_size)
+            // 服务端是否包含 `end` 处的记录并不确定，自行过滤
+            .try_skip_while(move |record| future::ready(Ok(record.time >= end)))
+            .try_take_while(move |record| future::ready(Ok(record.time >= start)))
+    }
+
+    /// 持续监听设备的新对话记录。
+    ///
+    /// 每隔 `poll_interval` 查询一次最近的对话记录，只产出比已见过的更新的记录，按时间从旧到新排列。
+    /// 开始监听前已有的记录不会产出。判断新旧依据的是已见过的记录中最大的时间，而不是本机的当前时间，
+    /// 因此不受本机与服务端时钟偏差的影响，时间相同的记录通过请求 ID 去重。
+    ///
+    /// 最新的一条记录可能先于应答出现，没有应答时会等到下一次查询再产出，直到有了应答或出现了更新的记录。
+    /// 两次查询之间的记录超过 [`WATCH_PAGE_SIZE`] 条时，较旧的部分会被漏掉。
+    ///
+    /// 查询出错时产出错误，但不会结束监听，可以自行决定是否继续。
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use miai::Xiaoai;
+    /// use futures_util::StreamExt;
+    ///
+    /// # async fn watch(xiaoai: Xiaoai) -> miai::Result<()> {
+    /// let records = xiaoai.watch_conversations("device_id", Duration::from_secs(3));
+    /// let mut records = std::pin::pin!(records);
+    /// while let Some(record) = records.next().await {
+    ///     println!("你问小爱: {}", record?.query);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn watch_conversations<'a>(
+        &'a self,
+        device_id: &'a str,
+        poll_interval: Duration,
+    ) -> impl Stream<Item = crate::Result<conversation::Record>> + 'a {
+        stream::unfold((true, None), move |(first, state)| async move {
+            if !first {
+                tokio::time::sleep(poll_interval).await;
+            }
+            let (result, state) = match state {
+                // 开始监听前出错时，下次重新开始
+                None => match self.start_watching(device_id).await {
+                    Ok(state) => (Ok(Vec::new()), Some(state)),
+                    Err(err) => (Err(err), None),
+                },
+                Some(mut state) => {
+                    let result = self.poll_conversations(device_id, &mut state).await;
+                    (result, Some(state))
+                }
+            };
+            let items: Vec<_> = match result {
+                Ok(records) => records.into_iter().map(Ok).collect(),
+                Err(err) => vec![Err(err)],
+            };
+
+            Some((stream::iter(items), (false, state)))
+        })
+        .flatten()
+    }
+
+    async fn start_watching(&self, device_id: &str) -> crate::Result<WatchState> {
+        let hardware = self.hardware(device_id).await?;
+        let mut state = WatchState {
+            hardware,
+            last_time: None,
+            seen: Vec::new(),
+        };
+        // 已有的记录只用来确定起点
+        for record in self.new_conversations(device_id, &state).await? {
+            state.mark_seen(&record);
+        }
+
+        Ok(state)
+    }
+
+    async fn poll_conversations(
+        &self,
+        device_id: &str,
+        state: &mut WatchState,
+    ) -> crate::Result<Vec<conversation::Record>> {
+        let mut records = self.new_conversations(device_id, state).await?;
+        if records
+            .last()
+            .is_some_and(|record| record.answers.is_empty())
+        {
+            records.pop();
+        }
+        for record in &records {
+            state.mark_seen(record);
+        }
+
+        Ok(records)
+    }
+
+    /// 查询比 `state` 中更新的记录，按时间从旧到新排列。
+    async fn new_conversations(
+        &self,
+        device_id: &str,
+        state: &WatchState,
+    ) -> crate::Result<Vec<conversation::Record>> {
+        // 服务端的时钟可能比本机快，稍微放宽
+        let until = OffsetDateTime::now_utc() + Duration::from_secs(10 * 60);
+        let mut records = self
+            .conversations(device_id, &state.hardware, until, WATCH_PAGE_SIZE)
+            .await?
+            .records;
+        records.retain(|record| state.is_new(record));
+        records.sort_by(|a, b| (a.time, &a.request_id).cmp(&(b.time, &b.request_id)));
+
+        Ok(records)
+    }
+
     /// 同 [`Self::conversations`]，但返回原始的响应。
     pub async fn raw_conversations(
         &self,
@@ -330,7 +2259,10 @@ impl Xiaoai {
             "https://userprofile.mina.mi.com/device_profile/v2/conversation?source=dialogu",
             &[
                 ("hardware", hardware),
-                ("timestamp", &(until.unix_timestamp() * 1000).to_string()),
+                (
+                    "timestamp",
+                    &(until.unix_timestamp_nanos() / 1_000_000).to_string(),
+                ),
                 ("limit", &limit.to_string()),
             ],
         )?;
@@ -343,38 +2275,162 @@ impl Xiaoai {
             .lock()
             .unwrap()
             .insert_raw(&cookie, &url)?;
-        let response: XiaoaiResponse = self.client.get(url).send().await?.json().await?;
+        let response = self.send(|| self.client.get(url.clone())).await?;
         trace!("获取到对话记录: {}", response.data);
 
         Ok(response)
     }
 }
 
-/// 表示播放器的播放状态。
-#[derive(Clone, Debug)]
-pub enum PlayState {
-    Play,
-    Pause,
-    Stop,
-    /// 在播放和暂停之间切换。
-    Toggle,
+/// [`Xiaoai::watch_conversations`] 每次查询的最大条数。
+pub const WATCH_PAGE_SIZE: u32 = 20;
+
+/// [`Xiaoai::watch_conversations`] 的状态。
+struct WatchState {
+    hardware: String,
+    /// 已见过的记录中最大的时间。
+    last_time: Option<OffsetDateTime>,
+    /// 时间为 `last_time` 的记录的请求 ID。
+    seen: Vec<String>,
+}
+
+impl WatchState {
+    fn is_new(&self, record: &conversation::Record) -> bool {
+        match self.last_time {
+            Some(last_time) if record.time == last_time => !self.seen.contains(&record.request_id),
+            Some(last_time) => record.time > last_time,
+            None => true,
+        }
+    }
+
+    fn mark_seen(&mut self, record: &conversation::Record) {
+        if self.last_time != Some(record.time) {
+            self.last_time = Some(record.time);
+            self.seen.clear();
+        }
+        self.seen.push(record.request_id.clone());
+    }
 }
 
 /// 小爱设备信息。
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DeviceInfo {
-    /// 设备 ID。
+    /// 设备 ID，即小爱服务（mina）的设备 ID。
     ///
-    /// 每个与设备相关的请求都会用 ID 指明对象。
+    /// 每个与设备相关的请求都会用 ID 指明对象，本库所有的方法都使用这个 ID。
     #[serde(rename = "deviceID")]
     pub device_id: String,
 
+    /// 设备在米家（miio）中的 did，与 [`Self::device_id`] 不同，没有时为 `None`。
+    ///
+    /// 只在调用米家的接口时需要，不能传给本库的方法。误传时 [`Xiaoai::device_info_one`] 和
+    /// [`Xiaoai::max_volume`] 等需要设备列表的方法会返回 [`Error::MiioDidGiven`][crate::Error::MiioDidGiven]；
+    /// 基于 UBUS 调用的方法则在请求失败后，借助已缓存的设备列表识别，参见 [`Xiaoai::ubus_call`]。
+    #[serde(
+        default,
+        rename = "miioDID",
+        alias = "miotDID",
+        deserialize_with = "string_or_number"
+    )]
+    pub miio_did: Option<String>,
+
     /// 设备名称。
     pub name: String,
 
     /// 机型。
     pub hardware: String,
+
+    /// 是否在线。
+    ///
+    /// 解析自设备列表的 `presence` 字段，响应中没有该字段时视为在线。
+    #[serde(default = "online", alias = "presence", deserialize_with = "presence")]
+    pub online: bool,
+
+    /// 支持的功能。
+    ///
+    /// 不同机型支持的操作不同，可以在请求前检查，参见 [`Capabilities`]。
+    #[serde(default)]
+    pub capabilities: Capabilities,
+}
+
+impl DeviceInfo {
+    /// 已知的机型信息，未知的机型返回 `None`，参见 [`DeviceModel`]。
+    pub fn model(&self) -> Option<&'static DeviceModel> {
+        DeviceModel::lookup(&self.hardware)
+    }
+}
+
+fn online() -> bool {
+    true
+}
+
+/// did 有时是数字。
+fn string_or_number<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    Ok(match Option::<Value>::deserialize(deserializer)? {
+        Some(Value::String(did)) if !did.is_empty() => Some(did),
+        Some(Value::Number(did)) => Some(did.to_string()),
+        _ => None,
+    })
+}
+
+/// `presence` 是 `"online"` 或 `"offline"`，序列化后则是布尔值，两者都需要支持。
+fn presence<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Bool(online) => online,
+        Value::String(presence) => presence != "offline",
+        _ => true,
+    })
+}
+
+/// 刷新登录状态后的回调。
+#[derive(Clone)]
+pub(crate) struct RefreshHook(Arc<dyn Fn(&Xiaoai) + Send + Sync>);
+
+impl fmt::Debug for RefreshHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RefreshHook")
+    }
+}
+
+/// 代替登录服务的刷新，参见 [`XiaoaiBuilder::mock_refresh`]。
+#[cfg(feature = "test-util")]
+#[derive(Clone)]
+pub(crate) struct MockRefresh(pub(crate) Arc<dyn Fn() -> crate::Result<()> + Send + Sync>);
+
+#[cfg(feature = "test-util")]
+impl fmt::Debug for MockRefresh {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MockRefresh")
+    }
+}
+
+/// 演练模式下代替真实响应的成功响应，参见 [`Xiaoai::with_dry_run`]。
+fn dry_run_response(request: reqwest::Request) -> XiaoaiResponse {
+    let url = redact::url(request.url());
+    let form: serde_json::Map<_, _> = request
+        .body()
+        .and_then(reqwest::Body::as_bytes)
+        .map(|body| {
+            url::form_urlencoded::parse(body)
+                .map(|(key, value)| (key.into_owned(), Value::from(value.into_owned())))
+                .collect()
+        })
+        .unwrap_or_default();
+    debug!("演练: {} {url} {form:?}", request.method());
+
+    XiaoaiResponse {
+        code: 0,
+        message: "Dry run".into(),
+        data: json!({
+            "dryRun": true,
+            "method": request.method().as_str(),
+            "url": url.as_str(),
+            "form": form,
+        }),
+    }
 }
 
 fn random_request_id() -> String {